@@ -0,0 +1,4 @@
+// migrations/ の変更時に sqlx::migrate!() を再コンパイルさせる
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS Budgets;
+DROP TABLE IF EXISTS Transactions;
+DROP TABLE IF EXISTS ChildCategories;
+DROP TABLE IF EXISTS ParentCategories;
+DROP TABLE IF EXISTS Accounts;
+DROP TABLE IF EXISTS Users;
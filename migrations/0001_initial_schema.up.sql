@@ -0,0 +1,56 @@
+-- db/init.sql で作成済みのデータベースでもそのまま適用できるようにする
+CREATE TABLE IF NOT EXISTS Users (
+    user_id SERIAL PRIMARY KEY,
+    username VARCHAR(50) NOT NULL,
+    user_email VARCHAR(100) NOT NULL UNIQUE,
+    user_password VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS Accounts (
+    account_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    account_name VARCHAR(50) NOT NULL,
+    initial_balance DECIMAL(10, 2) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES Users(user_id)
+);
+
+CREATE TABLE IF NOT EXISTS ParentCategories (
+    parent_category_id SERIAL PRIMARY KEY,
+    account_id INT NOT NULL,
+    parent_category_name VARCHAR(50) NOT NULL,
+    color VARCHAR(7) NOT NULL,
+    category_type INT NOT NULL CHECK (category_type IN (1, 2)),
+    FOREIGN KEY (account_id) REFERENCES Accounts(account_id)
+);
+
+CREATE TABLE IF NOT EXISTS ChildCategories (
+    child_category_id SERIAL PRIMARY KEY,
+    parent_category_id INT NOT NULL,
+    child_category_name VARCHAR(50) NOT NULL,
+    FOREIGN KEY (parent_category_id) REFERENCES ParentCategories(parent_category_id)
+);
+
+CREATE TABLE IF NOT EXISTS Transactions (
+    transaction_id SERIAL PRIMARY KEY,
+    account_id INT NOT NULL,
+    child_category_id INT NOT NULL,
+    transaction_amount DECIMAL(10, 2) NOT NULL,
+    transaction_type VARCHAR(7) NOT NULL CHECK (transaction_type IN ('income', 'expense')),
+    transaction_date DATE NOT NULL,
+    transaction_description TEXT,
+    FOREIGN KEY (account_id) REFERENCES Accounts(account_id),
+    FOREIGN KEY (child_category_id) REFERENCES ChildCategories(child_category_id)
+);
+
+CREATE TABLE IF NOT EXISTS Budgets (
+    budget_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    child_category_id INT NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES Users(user_id),
+    FOREIGN KEY (child_category_id) REFERENCES ChildCategories(child_category_id)
+);
@@ -0,0 +1,3 @@
+-- 口座ごとの取引一覧 (日付・ID の降順) のカーソルページング用
+CREATE INDEX IF NOT EXISTS transactions_account_date_idx
+    ON Transactions (account_id, transaction_date DESC, transaction_id DESC);
@@ -0,0 +1 @@
+DROP FUNCTION IF EXISTS account_balance(INT);
@@ -0,0 +1,11 @@
+-- 初期残高に収入を加え支出を差し引いた口座の現在残高
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + COALESCE((
+        SELECT SUM(CASE WHEN t.transaction_type = 'income' THEN t.transaction_amount ELSE -t.transaction_amount END)
+        FROM Transactions t
+        WHERE t.account_id = a.account_id
+    ), 0)
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,6 @@
+DELETE FROM Transactions WHERE transfer_id IS NOT NULL;
+DROP INDEX IF EXISTS transactions_transfer_id_idx;
+ALTER TABLE Transactions DROP CONSTRAINT IF EXISTS transactions_child_category_id_check;
+ALTER TABLE Transactions ALTER COLUMN child_category_id SET NOT NULL;
+ALTER TABLE Transactions DROP COLUMN IF EXISTS transfer_id;
+DROP TABLE IF EXISTS Transfers;
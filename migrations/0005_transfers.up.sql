@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS Transfers (
+    transfer_id SERIAL PRIMARY KEY,
+    from_account_id INT NOT NULL,
+    to_account_id INT NOT NULL,
+    transfer_amount DECIMAL(10, 2) NOT NULL CHECK (transfer_amount > 0),
+    transfer_date DATE NOT NULL,
+    transfer_description TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    CHECK (from_account_id <> to_account_id),
+    FOREIGN KEY (from_account_id) REFERENCES Accounts(account_id),
+    FOREIGN KEY (to_account_id) REFERENCES Accounts(account_id)
+);
+
+-- 振替は出金側 (expense) と入金側 (income) の 2 つの取引として記録し、カテゴリは持たない
+ALTER TABLE Transactions ADD COLUMN transfer_id INT REFERENCES Transfers(transfer_id) ON DELETE CASCADE;
+ALTER TABLE Transactions ALTER COLUMN child_category_id DROP NOT NULL;
+ALTER TABLE Transactions ADD CONSTRAINT transactions_child_category_id_check
+    CHECK (child_category_id IS NOT NULL OR transfer_id IS NOT NULL);
+
+CREATE INDEX IF NOT EXISTS transactions_transfer_id_idx ON Transactions (transfer_id);
@@ -0,0 +1,7 @@
+DROP FUNCTION IF EXISTS transaction_splits_json(INT);
+DROP VIEW IF EXISTS TransactionCategoryLines;
+-- カテゴリを分割行でのみ持っていた取引は戻せないため削除する
+DELETE FROM Transactions WHERE child_category_id IS NULL AND transfer_id IS NULL;
+DROP TABLE IF EXISTS TransactionSplits;
+ALTER TABLE Transactions ADD CONSTRAINT transactions_child_category_id_check
+    CHECK (child_category_id IS NOT NULL OR transfer_id IS NOT NULL);
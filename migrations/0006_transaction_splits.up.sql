@@ -0,0 +1,45 @@
+CREATE TABLE IF NOT EXISTS TransactionSplits (
+    split_id SERIAL PRIMARY KEY,
+    transaction_id INT NOT NULL,
+    child_category_id INT NOT NULL,
+    split_amount DECIMAL(10, 2) NOT NULL CHECK (split_amount > 0),
+    split_memo TEXT,
+    FOREIGN KEY (transaction_id) REFERENCES Transactions(transaction_id) ON DELETE CASCADE,
+    FOREIGN KEY (child_category_id) REFERENCES ChildCategories(child_category_id)
+);
+
+CREATE INDEX IF NOT EXISTS transactionsplits_transaction_id_idx ON TransactionSplits (transaction_id);
+
+-- 分割された取引はカテゴリを分割行で持つため、カテゴリの有無はアプリケーション側で検証する
+ALTER TABLE Transactions DROP CONSTRAINT IF EXISTS transactions_child_category_id_check;
+
+-- 取引をカテゴリ別の明細に展開したもの。分割行があればそれぞれのカテゴリに、
+-- なければ取引自身のカテゴリに計上する。振替はカテゴリを持たないため含まれない
+CREATE OR REPLACE VIEW TransactionCategoryLines AS
+SELECT t.transaction_id, t.account_id, s.child_category_id, s.split_amount AS amount, t.transaction_type, t.transaction_date
+FROM Transactions t
+JOIN TransactionSplits s ON s.transaction_id = t.transaction_id
+UNION ALL
+SELECT t.transaction_id, t.account_id, t.child_category_id, t.transaction_amount AS amount, t.transaction_type, t.transaction_date
+FROM Transactions t
+WHERE t.child_category_id IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id);
+
+-- API のレスポンスに埋め込む分割行の JSON 配列
+CREATE OR REPLACE FUNCTION transaction_splits_json(target_transaction_id INT)
+RETURNS JSON AS $$
+    SELECT COALESCE(
+        json_agg(
+            json_build_object(
+                'split_id', s.split_id,
+                'child_category_id', s.child_category_id,
+                'split_amount', s.split_amount::TEXT,
+                'split_memo', s.split_memo
+            )
+            ORDER BY s.split_id
+        ),
+        '[]'::JSON
+    )
+    FROM TransactionSplits s
+    WHERE s.transaction_id = target_transaction_id
+$$ LANGUAGE SQL STABLE;
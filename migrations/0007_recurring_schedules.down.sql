@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS transactions_schedule_occurrence_key;
+ALTER TABLE Transactions DROP COLUMN IF EXISTS occurrence_date;
+ALTER TABLE Transactions DROP COLUMN IF EXISTS schedule_id;
+DROP TABLE IF EXISTS RecurringSchedules;
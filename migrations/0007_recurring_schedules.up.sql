@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS RecurringSchedules (
+    schedule_id SERIAL PRIMARY KEY,
+    account_id INT NOT NULL,
+    child_category_id INT NOT NULL,
+    transaction_amount DECIMAL(10, 2) NOT NULL CHECK (transaction_amount > 0),
+    transaction_type VARCHAR(50) NOT NULL CHECK (transaction_type IN ('income', 'expense')),
+    transaction_description TEXT,
+    frequency VARCHAR(10) NOT NULL CHECK (frequency IN ('daily', 'weekly', 'monthly', 'yearly')),
+    interval_count INT NOT NULL DEFAULT 1 CHECK (interval_count > 0),
+    -- 月次の計上日。-1 は月末を表し、NULL なら start_date の日を使う
+    day_of_month INT CHECK (day_of_month BETWEEN 1 AND 31 OR day_of_month = -1),
+    start_date DATE NOT NULL,
+    end_date DATE,
+    occurrence_count INT CHECK (occurrence_count > 0),
+    -- この日付までの発生分は計上済み
+    posted_through DATE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    CHECK (end_date IS NULL OR end_date >= start_date),
+    FOREIGN KEY (account_id) REFERENCES Accounts(account_id),
+    FOREIGN KEY (child_category_id) REFERENCES ChildCategories(child_category_id)
+);
+
+CREATE INDEX IF NOT EXISTS recurringschedules_account_id_idx ON RecurringSchedules (account_id);
+
+-- 定期取引から計上された取引。スケジュールを削除しても計上済みの取引は残す
+ALTER TABLE Transactions ADD COLUMN schedule_id INT REFERENCES RecurringSchedules(schedule_id) ON DELETE SET NULL;
+ALTER TABLE Transactions ADD COLUMN occurrence_date DATE;
+
+-- 同じ発生日を二重に計上しないための一意制約
+CREATE UNIQUE INDEX IF NOT EXISTS transactions_schedule_occurrence_key
+    ON Transactions (schedule_id, occurrence_date) WHERE schedule_id IS NOT NULL;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS transactions_import_batch_id_idx;
+ALTER TABLE Transactions DROP COLUMN IF EXISTS import_batch_id;
+DROP TABLE IF EXISTS ImportBatches;
+DROP TABLE IF EXISTS ImportMappings;
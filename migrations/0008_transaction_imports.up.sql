@@ -0,0 +1,32 @@
+-- 銀行の明細 CSV の列の対応付け。口座ごとに保存して繰り返し使う
+CREATE TABLE IF NOT EXISTS ImportMappings (
+    mapping_id SERIAL PRIMARY KEY,
+    account_id INT NOT NULL,
+    mapping_name VARCHAR(50) NOT NULL,
+    date_column VARCHAR(100) NOT NULL,
+    date_format VARCHAR(50) NOT NULL DEFAULT '%Y-%m-%d',
+    amount_column VARCHAR(100) NOT NULL,
+    amount_sign VARCHAR(20) NOT NULL CHECK (amount_sign IN ('negative_expense', 'positive_expense')),
+    description_column VARCHAR(100),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT importmappings_mapping_name_key UNIQUE (account_id, mapping_name),
+    FOREIGN KEY (account_id) REFERENCES Accounts(account_id)
+);
+
+-- 取り込み 1 回分。削除すると取り込んだ取引もまとめて取り消される
+CREATE TABLE IF NOT EXISTS ImportBatches (
+    import_batch_id SERIAL PRIMARY KEY,
+    account_id INT NOT NULL,
+    mapping_id INT,
+    source VARCHAR(20) NOT NULL,
+    row_count INT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES Accounts(account_id),
+    FOREIGN KEY (mapping_id) REFERENCES ImportMappings(mapping_id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS importbatches_account_id_idx ON ImportBatches (account_id);
+
+ALTER TABLE Transactions ADD COLUMN import_batch_id INT REFERENCES ImportBatches(import_batch_id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS transactions_import_batch_id_idx ON Transactions (import_batch_id);
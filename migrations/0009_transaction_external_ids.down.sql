@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS transactions_external_id_key;
+ALTER TABLE Transactions DROP COLUMN IF EXISTS external_id;
@@ -0,0 +1,5 @@
+-- 銀行側の取引 ID (OFX の FITID、CAMT.053 の AcctSvcrRef)。同じ明細を再度取り込んでも重複させない
+ALTER TABLE Transactions ADD COLUMN external_id VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS transactions_external_id_key
+    ON Transactions (account_id, external_id) WHERE external_id IS NOT NULL;
@@ -0,0 +1,2 @@
+DROP FUNCTION IF EXISTS budget_progress(INT, DATE);
+DROP FUNCTION IF EXISTS budget_spent(INT);
@@ -0,0 +1,32 @@
+-- 予算の期間中に、予算のカテゴリへ計上された支出の合計 (ユーザーの全口座が対象)。
+-- 分割された取引は分割行ごとに、振替は含めずに集計する
+CREATE OR REPLACE FUNCTION budget_spent(target_budget_id INT)
+RETURNS DECIMAL AS $$
+    SELECT COALESCE(SUM(l.amount), 0)
+    FROM Budgets b
+    JOIN TransactionCategoryLines l
+        ON l.child_category_id = b.child_category_id
+        AND l.transaction_type = 'expense'
+        AND l.transaction_date BETWEEN b.start_date AND b.end_date
+    JOIN Accounts a ON a.account_id = l.account_id AND a.user_id = b.user_id
+    WHERE b.budget_id = target_budget_id
+$$ LANGUAGE SQL STABLE;
+
+-- as_of 時点の予算の消化状況。期末の見込みは期間の経過日数に対するこれまでのペースで延長する
+CREATE OR REPLACE FUNCTION budget_progress(target_budget_id INT, as_of DATE)
+RETURNS TABLE (spent DECIMAL, remaining DECIMAL, percent_used DECIMAL, projected_spending DECIMAL) AS $$
+    WITH progress AS (
+        SELECT b.amount, b.start_date, b.end_date, budget_spent(b.budget_id) AS total_spent
+        FROM Budgets b
+        WHERE b.budget_id = target_budget_id
+    )
+    SELECT
+        total_spent,
+        amount - total_spent,
+        ROUND(total_spent * 100 / amount, 1),
+        ROUND(CASE
+            WHEN as_of < start_date OR as_of >= end_date THEN total_spent
+            ELSE total_spent * (end_date - start_date + 1) / (as_of - start_date + 1)
+        END, 2)
+    FROM progress
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,22 @@
+CREATE OR REPLACE FUNCTION budget_progress(target_budget_id INT, as_of DATE)
+RETURNS TABLE (spent DECIMAL, remaining DECIMAL, percent_used DECIMAL, projected_spending DECIMAL) AS $$
+    WITH progress AS (
+        SELECT b.amount, b.start_date, b.end_date, budget_spent(b.budget_id) AS total_spent
+        FROM Budgets b
+        WHERE b.budget_id = target_budget_id
+    )
+    SELECT
+        total_spent,
+        amount - total_spent,
+        ROUND(total_spent * 100 / amount, 1),
+        ROUND(CASE
+            WHEN as_of < start_date OR as_of >= end_date THEN total_spent
+            ELSE total_spent * (end_date - start_date + 1) / (as_of - start_date + 1)
+        END, 2)
+    FROM progress
+$$ LANGUAGE SQL STABLE;
+
+DROP INDEX IF EXISTS budgets_template_period_key;
+ALTER TABLE Budgets DROP COLUMN IF EXISTS rollover_amount;
+ALTER TABLE Budgets DROP COLUMN IF EXISTS template_id;
+DROP TABLE IF EXISTS BudgetTemplates;
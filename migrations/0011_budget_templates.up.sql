@@ -0,0 +1,50 @@
+-- 期間ごとに予算を自動作成するためのテンプレート。期間は anchor_date から
+-- interval_count 単位ずつ区切り、各期間は次の期間の開始日の前日で終わる
+CREATE TABLE IF NOT EXISTS BudgetTemplates (
+    template_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    child_category_id INT NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL CHECK (amount > 0),
+    period VARCHAR(10) NOT NULL CHECK (period IN ('weekly', 'monthly', 'yearly')),
+    interval_count INT NOT NULL DEFAULT 1 CHECK (interval_count > 0),
+    anchor_date DATE NOT NULL,
+    end_date DATE,
+    -- 前の期間の残額 (unspent) または超過額 (overspent) を次の期間に繰り越すか
+    rollover VARCHAR(10) NOT NULL DEFAULT 'none' CHECK (rollover IN ('none', 'unspent', 'overspent', 'both')),
+    -- 作成済みの最後の期間の開始日
+    generated_through DATE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    CHECK (end_date IS NULL OR end_date >= anchor_date),
+    FOREIGN KEY (user_id) REFERENCES Users(user_id),
+    FOREIGN KEY (child_category_id) REFERENCES ChildCategories(child_category_id)
+);
+
+CREATE INDEX IF NOT EXISTS budgettemplates_user_id_idx ON BudgetTemplates (user_id);
+
+-- テンプレートから作成された予算。テンプレートを削除しても作成済みの予算は残す
+ALTER TABLE Budgets ADD COLUMN template_id INT REFERENCES BudgetTemplates(template_id) ON DELETE SET NULL;
+-- 前の期間から繰り越した金額。負の値は超過分の持ち越し
+ALTER TABLE Budgets ADD COLUMN rollover_amount DECIMAL(10, 2) NOT NULL DEFAULT 0;
+
+-- 同じ期間の予算を二重に作成しないための一意制約
+CREATE UNIQUE INDEX IF NOT EXISTS budgets_template_period_key
+    ON Budgets (template_id, start_date) WHERE template_id IS NOT NULL;
+
+-- 繰越額を含めた金額に対して消化状況を計算する
+CREATE OR REPLACE FUNCTION budget_progress(target_budget_id INT, as_of DATE)
+RETURNS TABLE (spent DECIMAL, remaining DECIMAL, percent_used DECIMAL, projected_spending DECIMAL) AS $$
+    WITH progress AS (
+        SELECT b.amount + b.rollover_amount AS available, b.start_date, b.end_date, budget_spent(b.budget_id) AS total_spent
+        FROM Budgets b
+        WHERE b.budget_id = target_budget_id
+    )
+    SELECT
+        total_spent,
+        available - total_spent,
+        CASE WHEN available > 0 THEN ROUND(total_spent * 100 / available, 1) END,
+        ROUND(CASE
+            WHEN as_of < start_date OR as_of >= end_date THEN total_spent
+            ELSE total_spent * (end_date - start_date + 1) / (as_of - start_date + 1)
+        END, 2)
+    FROM progress
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS notifications_budget_threshold_key;
+DROP TABLE IF EXISTS Notifications;
+ALTER TABLE Budgets DROP COLUMN IF EXISTS alert_thresholds;
@@ -0,0 +1,25 @@
+-- 消化率 (%) がこの値に達したときに通知する
+ALTER TABLE Budgets ADD COLUMN alert_thresholds INT[] NOT NULL DEFAULT '{80,100}';
+
+-- 利用者への通知。送信されるまでは配信待ちの outbox として扱う
+CREATE TABLE IF NOT EXISTS Notifications (
+    notification_id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    budget_id INT REFERENCES Budgets(budget_id) ON DELETE CASCADE,
+    threshold INT,
+    notification_title VARCHAR(255) NOT NULL,
+    notification_message TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    read_at TIMESTAMP,
+    delivered_at TIMESTAMP,
+    delivery_attempts INT NOT NULL DEFAULT 0,
+    last_delivery_error TEXT,
+    FOREIGN KEY (user_id) REFERENCES Users(user_id)
+);
+
+CREATE INDEX IF NOT EXISTS notifications_user_id_idx ON Notifications (user_id, notification_id);
+CREATE INDEX IF NOT EXISTS notifications_undelivered_idx ON Notifications (notification_id) WHERE delivered_at IS NULL;
+
+-- 同じ予算の同じしきい値は 1 度だけ通知する
+CREATE UNIQUE INDEX IF NOT EXISTS notifications_budget_threshold_key
+    ON Notifications (budget_id, threshold) WHERE budget_id IS NOT NULL;
@@ -0,0 +1,33 @@
+DROP FUNCTION IF EXISTS base_amount(DECIMAL, CHAR(3), DATE, INT);
+
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + COALESCE((
+        SELECT SUM(CASE WHEN t.transaction_type = 'income' THEN t.transaction_amount ELSE -t.transaction_amount END)
+        FROM Transactions t
+        WHERE t.account_id = a.account_id
+    ), 0)
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+DROP FUNCTION IF EXISTS account_amount(DECIMAL, CHAR(3), DATE, INT);
+
+-- 列を減らすため作り直す
+DROP VIEW IF EXISTS TransactionCategoryLines;
+CREATE VIEW TransactionCategoryLines AS
+SELECT t.transaction_id, t.account_id, s.child_category_id, s.split_amount AS amount, t.transaction_type, t.transaction_date
+FROM Transactions t
+JOIN TransactionSplits s ON s.transaction_id = t.transaction_id
+UNION ALL
+SELECT t.transaction_id, t.account_id, t.child_category_id, t.transaction_amount AS amount, t.transaction_type, t.transaction_date
+FROM Transactions t
+WHERE t.child_category_id IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id);
+
+DROP FUNCTION IF EXISTS exchange_rate(CHAR(3), CHAR(3), DATE, INT);
+DROP INDEX IF EXISTS exchangerates_rate_key;
+DROP TABLE IF EXISTS ExchangeRates;
+ALTER TABLE Transactions DROP COLUMN IF EXISTS currency;
+ALTER TABLE Accounts DROP COLUMN IF EXISTS currency;
+ALTER TABLE Users DROP COLUMN IF EXISTS base_currency;
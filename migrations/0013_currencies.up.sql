@@ -0,0 +1,80 @@
+-- ISO 4217 の通貨コード。既存のデータは USD として扱う
+ALTER TABLE Users ADD COLUMN base_currency CHAR(3) NOT NULL DEFAULT 'USD' CHECK (base_currency ~ '^[A-Z]{3}$');
+ALTER TABLE Accounts ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' CHECK (currency ~ '^[A-Z]{3}$');
+-- 口座と異なる通貨で記録した取引の通貨。NULL なら口座の通貨
+ALTER TABLE Transactions ADD COLUMN currency CHAR(3) CHECK (currency ~ '^[A-Z]{3}$');
+
+-- 1 base_currency = rate quote_currency。user_id が NULL の行はファイルから読み込んだ全ユーザー共通のレート
+CREATE TABLE IF NOT EXISTS ExchangeRates (
+    exchange_rate_id SERIAL PRIMARY KEY,
+    user_id INT REFERENCES Users(user_id) ON DELETE CASCADE,
+    rate_date DATE NOT NULL,
+    base_currency CHAR(3) NOT NULL CHECK (base_currency ~ '^[A-Z]{3}$'),
+    quote_currency CHAR(3) NOT NULL CHECK (quote_currency ~ '^[A-Z]{3}$'),
+    rate DECIMAL(20, 10) NOT NULL CHECK (rate > 0),
+    CHECK (base_currency <> quote_currency)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS exchangerates_rate_key
+    ON ExchangeRates ((COALESCE(user_id, 0)), base_currency, quote_currency, rate_date);
+
+-- on_date 以前で最も新しいレートで from_currency を to_currency に換算する倍率。逆向きのレートも使い、
+-- 同じ日付ではユーザー自身のレートを共通のレートより優先する。レートがなければ NULL
+CREATE OR REPLACE FUNCTION exchange_rate(from_currency CHAR(3), to_currency CHAR(3), on_date DATE, target_user_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN from_currency = to_currency THEN 1 ELSE (
+        SELECT CASE WHEN r.base_currency = from_currency THEN r.rate ELSE 1 / r.rate END
+        FROM ExchangeRates r
+        WHERE ((r.base_currency = from_currency AND r.quote_currency = to_currency)
+                OR (r.base_currency = to_currency AND r.quote_currency = from_currency))
+            AND r.rate_date <= on_date
+            AND (r.user_id IS NULL OR r.user_id = target_user_id)
+        ORDER BY r.rate_date DESC, r.user_id IS NULL, r.base_currency = from_currency DESC
+        LIMIT 1
+    ) END
+$$ LANGUAGE SQL STABLE;
+
+-- currency は取引の通貨 (指定がなければ口座の通貨)
+CREATE OR REPLACE VIEW TransactionCategoryLines AS
+SELECT t.transaction_id, t.account_id, s.child_category_id, s.split_amount AS amount, t.transaction_type, t.transaction_date,
+    COALESCE(t.currency, a.currency) AS currency
+FROM Transactions t
+JOIN Accounts a ON a.account_id = t.account_id
+JOIN TransactionSplits s ON s.transaction_id = t.transaction_id
+UNION ALL
+SELECT t.transaction_id, t.account_id, t.child_category_id, t.transaction_amount AS amount, t.transaction_type, t.transaction_date,
+    COALESCE(t.currency, a.currency) AS currency
+FROM Transactions t
+JOIN Accounts a ON a.account_id = t.account_id
+WHERE t.child_category_id IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id);
+
+-- 口座と異なる通貨の取引は取引日のレートで口座の通貨に換算する。レートが削除された場合は換算しない
+CREATE OR REPLACE FUNCTION account_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN transaction_currency IS NULL OR transaction_currency = a.currency THEN amount
+        ELSE ROUND(amount * COALESCE(exchange_rate(transaction_currency, a.currency, transaction_date, a.user_id), 1), 2)
+    END
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + COALESCE((
+        SELECT SUM(CASE WHEN t.transaction_type = 'income' THEN 1 ELSE -1 END
+            * account_amount(t.transaction_amount, t.currency, t.transaction_date, t.account_id))
+        FROM Transactions t
+        WHERE t.account_id = a.account_id
+    ), 0)
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+-- レポート用に取引日のレートで利用者の基準通貨に換算する。レートがなければ NULL
+CREATE OR REPLACE FUNCTION base_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_user_id INT)
+RETURNS DECIMAL AS $$
+    SELECT ROUND(amount * exchange_rate(transaction_currency, u.base_currency, transaction_date, u.user_id), 2)
+    FROM Users u
+    WHERE u.user_id = target_user_id
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,18 @@
+CREATE OR REPLACE FUNCTION base_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_user_id INT)
+RETURNS DECIMAL AS $$
+    SELECT ROUND(amount * exchange_rate(transaction_currency, u.base_currency, transaction_date, u.user_id), 2)
+    FROM Users u
+    WHERE u.user_id = target_user_id
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION account_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN transaction_currency IS NULL OR transaction_currency = a.currency THEN amount
+        ELSE ROUND(amount * COALESCE(exchange_rate(transaction_currency, a.currency, transaction_date, a.user_id), 1), 2)
+    END
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+DROP FUNCTION IF EXISTS round_half_even(DECIMAL, INT);
+DROP FUNCTION IF EXISTS currency_scale(CHAR(3));
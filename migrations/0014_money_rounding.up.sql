@@ -0,0 +1,34 @@
+-- 通貨の小数点以下の桁数。src/models/money.rs の minor_units と同じく、補助単位が 3 桁の通貨も 2 桁までとする
+CREATE OR REPLACE FUNCTION currency_scale(currency CHAR(3))
+RETURNS INT AS $$
+    SELECT CASE WHEN currency IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF', 'UGX', 'UYI', 'VND', 'VUV', 'XAF', 'XOF', 'XPF')
+        THEN 0 ELSE 2 END
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- 銀行丸め (最近接偶数への丸め)。ROUND はちょうど半分のときに 0 から遠い方へ丸める
+CREATE OR REPLACE FUNCTION round_half_even(value DECIMAL, digits INT)
+RETURNS DECIMAL AS $$
+    SELECT ROUND(
+        CASE WHEN ABS(shifted - TRUNC(shifted)) = 0.5 AND MOD(TRUNC(shifted), 2) = 0 THEN TRUNC(shifted) ELSE ROUND(shifted) END
+            / 10::DECIMAL ^ digits,
+        digits
+    )
+    FROM (SELECT value * 10::DECIMAL ^ digits AS shifted) s
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- 換算した金額は換算先の通貨の桁数に銀行丸めで丸める
+CREATE OR REPLACE FUNCTION account_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN transaction_currency IS NULL OR transaction_currency = a.currency THEN amount
+        ELSE round_half_even(amount * COALESCE(exchange_rate(transaction_currency, a.currency, transaction_date, a.user_id), 1), currency_scale(a.currency))
+    END
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION base_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_user_id INT)
+RETURNS DECIMAL AS $$
+    SELECT round_half_even(amount * exchange_rate(transaction_currency, u.base_currency, transaction_date, u.user_id), currency_scale(u.base_currency))
+    FROM Users u
+    WHERE u.user_id = target_user_id
+$$ LANGUAGE SQL STABLE;
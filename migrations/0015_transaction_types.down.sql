@@ -0,0 +1,59 @@
+-- 負の金額の取引は逆の種別の正の金額に戻す。別の取引に移した分割行はそのまま残す
+DROP VIEW IF EXISTS TransactionCategoryLines;
+
+UPDATE TransactionSplits SET split_amount = -split_amount WHERE split_amount < 0;
+UPDATE Transactions SET transaction_type = 3 - transaction_type, transaction_amount = -transaction_amount WHERE transaction_amount < 0;
+UPDATE RecurringSchedules SET transaction_type = 3 - transaction_type, transaction_amount = -transaction_amount WHERE transaction_amount < 0;
+
+ALTER TABLE TransactionSplits DROP CONSTRAINT IF EXISTS transactionsplits_split_amount_check;
+ALTER TABLE TransactionSplits ADD CONSTRAINT transactionsplits_split_amount_check CHECK (split_amount > 0);
+ALTER TABLE RecurringSchedules DROP CONSTRAINT IF EXISTS recurringschedules_transaction_amount_check;
+ALTER TABLE RecurringSchedules ADD CONSTRAINT recurringschedules_transaction_amount_check CHECK (transaction_amount > 0);
+
+ALTER TABLE RecurringSchedules DROP CONSTRAINT IF EXISTS recurringschedules_transaction_type_check;
+ALTER TABLE RecurringSchedules ALTER COLUMN transaction_type TYPE VARCHAR(50)
+    USING CASE transaction_type WHEN 1 THEN 'income' ELSE 'expense' END;
+ALTER TABLE RecurringSchedules ADD CONSTRAINT recurringschedules_transaction_type_check CHECK (transaction_type IN ('income', 'expense'));
+
+ALTER TABLE Transactions DROP CONSTRAINT IF EXISTS transactions_transaction_type_check;
+ALTER TABLE Transactions ALTER COLUMN transaction_type TYPE VARCHAR(7)
+    USING CASE transaction_type WHEN 1 THEN 'income' ELSE 'expense' END;
+ALTER TABLE Transactions ADD CONSTRAINT transactions_transaction_type_check CHECK (transaction_type IN ('income', 'expense'));
+
+CREATE VIEW TransactionCategoryLines AS
+SELECT t.transaction_id, t.account_id, s.child_category_id, s.split_amount AS amount, t.transaction_type, t.transaction_date,
+    COALESCE(t.currency, a.currency) AS currency
+FROM Transactions t
+JOIN Accounts a ON a.account_id = t.account_id
+JOIN TransactionSplits s ON s.transaction_id = t.transaction_id
+UNION ALL
+SELECT t.transaction_id, t.account_id, t.child_category_id, t.transaction_amount AS amount, t.transaction_type, t.transaction_date,
+    COALESCE(t.currency, a.currency) AS currency
+FROM Transactions t
+JOIN Accounts a ON a.account_id = t.account_id
+WHERE t.child_category_id IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id);
+
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + COALESCE((
+        SELECT SUM(CASE WHEN t.transaction_type = 'income' THEN 1 ELSE -1 END
+            * account_amount(t.transaction_amount, t.currency, t.transaction_date, t.account_id))
+        FROM Transactions t
+        WHERE t.account_id = a.account_id
+    ), 0)
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION budget_spent(target_budget_id INT)
+RETURNS DECIMAL AS $$
+    SELECT COALESCE(SUM(l.amount), 0)
+    FROM Budgets b
+    JOIN TransactionCategoryLines l
+        ON l.child_category_id = b.child_category_id
+        AND l.transaction_type = 'expense'
+        AND l.transaction_date BETWEEN b.start_date AND b.end_date
+    JOIN Accounts a ON a.account_id = l.account_id AND a.user_id = b.user_id
+    WHERE b.budget_id = target_budget_id
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,122 @@
+-- 取引と定期取引の種別を親カテゴリの category_type と同じ整数 (1: 収入, 2: 支出) で保存する。
+-- 種別はカテゴリの種別に揃え、支出カテゴリへの返金のような逆向きのお金の動きは負の金額で表す
+
+-- 列の型を変えるためビューを作り直す
+DROP VIEW IF EXISTS TransactionCategoryLines;
+
+ALTER TABLE Transactions DROP CONSTRAINT IF EXISTS transactions_transaction_type_check;
+ALTER TABLE Transactions ALTER COLUMN transaction_type TYPE INT
+    USING CASE transaction_type WHEN 'income' THEN 1 ELSE 2 END;
+ALTER TABLE Transactions ADD CONSTRAINT transactions_transaction_type_check CHECK (transaction_type IN (1, 2));
+
+ALTER TABLE RecurringSchedules DROP CONSTRAINT IF EXISTS recurringschedules_transaction_type_check;
+ALTER TABLE RecurringSchedules ALTER COLUMN transaction_type TYPE INT
+    USING CASE transaction_type WHEN 'income' THEN 1 ELSE 2 END;
+ALTER TABLE RecurringSchedules ADD CONSTRAINT recurringschedules_transaction_type_check CHECK (transaction_type IN (1, 2));
+
+ALTER TABLE TransactionSplits DROP CONSTRAINT IF EXISTS transactionsplits_split_amount_check;
+ALTER TABLE TransactionSplits ADD CONSTRAINT transactionsplits_split_amount_check CHECK (split_amount <> 0);
+ALTER TABLE RecurringSchedules DROP CONSTRAINT IF EXISTS recurringschedules_transaction_amount_check;
+ALTER TABLE RecurringSchedules ADD CONSTRAINT recurringschedules_transaction_amount_check CHECK (transaction_amount <> 0);
+
+-- 収入と支出のカテゴリが混在する分割取引は、取引と逆の種別のカテゴリの分割行を同じ日付の別の取引に移す。
+-- 移した分割行は符号を反転するため、口座の残高は変わらない
+DO $$
+DECLARE
+    mixed RECORD;
+    moved_transaction_id INT;
+BEGIN
+    FOR mixed IN
+        SELECT s.transaction_id, t.transaction_type, SUM(s.split_amount) AS moved_amount
+        FROM TransactionSplits s
+        JOIN Transactions t ON t.transaction_id = s.transaction_id
+        JOIN ChildCategories c ON c.child_category_id = s.child_category_id
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        WHERE p.category_type <> t.transaction_type
+        GROUP BY s.transaction_id, t.transaction_type
+        HAVING COUNT(*) < (SELECT COUNT(*) FROM TransactionSplits a WHERE a.transaction_id = s.transaction_id)
+    LOOP
+        INSERT INTO Transactions (account_id, transaction_amount, transaction_type, transaction_date, transaction_description, currency)
+        SELECT account_id, -mixed.moved_amount, 3 - transaction_type, transaction_date, transaction_description, currency
+        FROM Transactions
+        WHERE transaction_id = mixed.transaction_id
+        RETURNING transaction_id INTO moved_transaction_id;
+
+        UPDATE TransactionSplits s
+        SET transaction_id = moved_transaction_id, split_amount = -s.split_amount
+        FROM ChildCategories c
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        WHERE s.transaction_id = mixed.transaction_id
+            AND c.child_category_id = s.child_category_id
+            AND p.category_type <> mixed.transaction_type;
+
+        UPDATE Transactions
+        SET transaction_amount = transaction_amount - mixed.moved_amount
+        WHERE transaction_id = mixed.transaction_id;
+    END LOOP;
+END $$;
+
+-- 残りのカテゴリと逆の種別の取引は、種別をカテゴリに揃えて金額の符号を反転する。分割行を先に反転する
+UPDATE TransactionSplits s
+SET split_amount = -s.split_amount
+FROM Transactions t, ChildCategories c, ParentCategories p
+WHERE t.transaction_id = s.transaction_id
+    AND c.child_category_id = s.child_category_id
+    AND p.parent_category_id = c.parent_category_id
+    AND p.category_type <> t.transaction_type;
+
+UPDATE Transactions t
+SET transaction_type = p.category_type, transaction_amount = -t.transaction_amount
+FROM ChildCategories c, ParentCategories p
+WHERE c.child_category_id = COALESCE(
+        t.child_category_id,
+        (SELECT s.child_category_id FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id LIMIT 1)
+    )
+    AND p.parent_category_id = c.parent_category_id
+    AND p.category_type <> t.transaction_type;
+
+UPDATE RecurringSchedules r
+SET transaction_type = p.category_type, transaction_amount = -r.transaction_amount
+FROM ChildCategories c, ParentCategories p
+WHERE c.child_category_id = r.child_category_id
+    AND p.parent_category_id = c.parent_category_id
+    AND p.category_type <> r.transaction_type;
+
+CREATE VIEW TransactionCategoryLines AS
+SELECT t.transaction_id, t.account_id, s.child_category_id, s.split_amount AS amount, t.transaction_type, t.transaction_date,
+    COALESCE(t.currency, a.currency) AS currency
+FROM Transactions t
+JOIN Accounts a ON a.account_id = t.account_id
+JOIN TransactionSplits s ON s.transaction_id = t.transaction_id
+UNION ALL
+SELECT t.transaction_id, t.account_id, t.child_category_id, t.transaction_amount AS amount, t.transaction_type, t.transaction_date,
+    COALESCE(t.currency, a.currency) AS currency
+FROM Transactions t
+JOIN Accounts a ON a.account_id = t.account_id
+WHERE t.child_category_id IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id);
+
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + COALESCE((
+        SELECT SUM(CASE WHEN t.transaction_type = 1 THEN 1 ELSE -1 END
+            * account_amount(t.transaction_amount, t.currency, t.transaction_date, t.account_id))
+        FROM Transactions t
+        WHERE t.account_id = a.account_id
+    ), 0)
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+-- 返金は負の金額の支出として支出の合計から差し引かれる
+CREATE OR REPLACE FUNCTION budget_spent(target_budget_id INT)
+RETURNS DECIMAL AS $$
+    SELECT COALESCE(SUM(l.amount), 0)
+    FROM Budgets b
+    JOIN TransactionCategoryLines l
+        ON l.child_category_id = b.child_category_id
+        AND l.transaction_type = 2
+        AND l.transaction_date BETWEEN b.start_date AND b.end_date
+    JOIN Accounts a ON a.account_id = l.account_id AND a.user_id = b.user_id
+    WHERE b.budget_id = target_budget_id
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,11 @@
+CREATE OR REPLACE FUNCTION budget_spent(target_budget_id INT)
+RETURNS DECIMAL AS $$
+    SELECT COALESCE(SUM(l.amount), 0)
+    FROM Budgets b
+    JOIN TransactionCategoryLines l
+        ON l.child_category_id = b.child_category_id
+        AND l.transaction_type = 2
+        AND l.transaction_date BETWEEN b.start_date AND b.end_date
+    JOIN Accounts a ON a.account_id = l.account_id AND a.user_id = b.user_id
+    WHERE b.budget_id = target_budget_id
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,13 @@
+-- 予算は利用者の基準通貨で設定するため、口座の通貨の取引は取引日のレートで基準通貨に換算して合計する。
+-- レートがなければ account_amount と同じく換算せずに計上する
+CREATE OR REPLACE FUNCTION budget_spent(target_budget_id INT)
+RETURNS DECIMAL AS $$
+    SELECT COALESCE(SUM(COALESCE(base_amount(l.amount, l.currency, l.transaction_date, a.user_id), l.amount)), 0)
+    FROM Budgets b
+    JOIN TransactionCategoryLines l
+        ON l.child_category_id = b.child_category_id
+        AND l.transaction_type = 2
+        AND l.transaction_date BETWEEN b.start_date AND b.end_date
+    JOIN Accounts a ON a.account_id = l.account_id AND a.user_id = b.user_id
+    WHERE b.budget_id = target_budget_id
+$$ LANGUAGE SQL STABLE;
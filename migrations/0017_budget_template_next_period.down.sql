@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS budgettemplates_next_period_start_idx;
+ALTER TABLE BudgetTemplates DROP COLUMN IF EXISTS next_period_start;
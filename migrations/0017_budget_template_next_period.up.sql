@@ -0,0 +1,7 @@
+-- 次に作成する期間の開始日。作成する期間が残っていないテンプレートは NULL になり、定期処理の対象から外れる
+ALTER TABLE BudgetTemplates ADD COLUMN next_period_start DATE;
+
+-- 既存のテンプレートは次の定期処理で作成済みの期間を読み飛ばして正しい値に更新される
+UPDATE BudgetTemplates SET next_period_start = anchor_date;
+
+CREATE INDEX IF NOT EXISTS budgettemplates_next_period_start_idx ON BudgetTemplates (next_period_start);
@@ -0,0 +1 @@
+ALTER TABLE Notifications DROP COLUMN IF EXISTS delivery_claimed_until;
@@ -0,0 +1,2 @@
+-- 配信中の通知をこの時刻まで他の配信処理から除外する。配信処理が途中で止まった場合は期限を過ぎると再送される
+ALTER TABLE Notifications ADD COLUMN delivery_claimed_until TIMESTAMP;
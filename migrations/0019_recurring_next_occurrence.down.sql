@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS recurringschedules_next_occurrence_idx;
+ALTER TABLE RecurringSchedules DROP COLUMN IF EXISTS next_occurrence;
@@ -0,0 +1,7 @@
+-- 次に計上する発生日。発生日が残っていないスケジュールは NULL になり、定期処理の対象から外れる
+ALTER TABLE RecurringSchedules ADD COLUMN next_occurrence DATE;
+
+-- 既存のスケジュールは次の定期処理で計上済みの発生日を読み飛ばして正しい値に更新される
+UPDATE RecurringSchedules SET next_occurrence = start_date;
+
+CREATE INDEX IF NOT EXISTS recurringschedules_next_occurrence_idx ON RecurringSchedules (next_occurrence);
@@ -0,0 +1,16 @@
+-- 基準通貨に換算できない取引があれば、通貨の異なる金額を混ぜて合計しないよう NULL を返す。
+-- このとき消化状況も NULL になり、しきい値の通知も行わない
+CREATE OR REPLACE FUNCTION budget_spent(target_budget_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN COUNT(*) = COUNT(s.amount) THEN COALESCE(SUM(s.amount), 0) END
+    FROM (
+        SELECT base_amount(l.amount, l.currency, l.transaction_date, a.user_id) AS amount
+        FROM Budgets b
+        JOIN TransactionCategoryLines l
+            ON l.child_category_id = b.child_category_id
+            AND l.transaction_type = 2
+            AND l.transaction_date BETWEEN b.start_date AND b.end_date
+        JOIN Accounts a ON a.account_id = l.account_id AND a.user_id = b.user_id
+        WHERE b.budget_id = target_budget_id
+    ) s
+$$ LANGUAGE SQL STABLE;
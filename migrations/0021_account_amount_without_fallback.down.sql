@@ -0,0 +1,20 @@
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + COALESCE((
+        SELECT SUM(CASE WHEN t.transaction_type = 1 THEN 1 ELSE -1 END
+            * account_amount(t.transaction_amount, t.currency, t.transaction_date, t.account_id))
+        FROM Transactions t
+        WHERE t.account_id = a.account_id
+    ), 0)
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION account_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN transaction_currency IS NULL OR transaction_currency = a.currency THEN amount
+        ELSE round_half_even(amount * COALESCE(exchange_rate(transaction_currency, a.currency, transaction_date, a.user_id), 1), currency_scale(a.currency))
+    END
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
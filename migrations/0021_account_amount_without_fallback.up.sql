@@ -0,0 +1,26 @@
+-- 口座の通貨に換算できない取引の金額を 1:1 で計上しないよう、レートがなければ NULL を返す。
+-- 外貨の取引はレートがなければ登録できないため、通常は既存の取引にだけ起こる
+CREATE OR REPLACE FUNCTION account_amount(amount DECIMAL, transaction_currency CHAR(3), transaction_date DATE, target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT CASE WHEN transaction_currency IS NULL OR transaction_currency = a.currency THEN amount
+        ELSE round_half_even(amount * exchange_rate(transaction_currency, a.currency, transaction_date, a.user_id), currency_scale(a.currency))
+    END
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
+
+-- 換算できない取引があれば、誤った残高を返さないよう NULL を返す
+CREATE OR REPLACE FUNCTION account_balance(target_account_id INT)
+RETURNS DECIMAL AS $$
+    SELECT a.initial_balance + (
+        SELECT CASE WHEN COUNT(*) = COUNT(s.amount) THEN COALESCE(SUM(s.amount), 0) END
+        FROM (
+            SELECT CASE WHEN t.transaction_type = 1 THEN 1 ELSE -1 END
+                * account_amount(t.transaction_amount, t.currency, t.transaction_date, t.account_id) AS amount
+            FROM Transactions t
+            WHERE t.account_id = a.account_id
+        ) s
+    )
+    FROM Accounts a
+    WHERE a.account_id = target_account_id
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS transactionsplits_category_check ON TransactionSplits;
+DROP TRIGGER IF EXISTS transactions_category_check ON Transactions;
+DROP FUNCTION IF EXISTS check_transaction_category();
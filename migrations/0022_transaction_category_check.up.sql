@@ -0,0 +1,31 @@
+-- 振替以外の取引は、取引自身のカテゴリか 1 行以上の分割行のどちらかを持つ。
+-- 取引と分割行は別々に書き込むため、トランザクションの終わりに確認する
+CREATE OR REPLACE FUNCTION check_transaction_category()
+RETURNS TRIGGER AS $$
+DECLARE
+    -- 分割行は削除・変更される前の取引を確認する
+    target_transaction_id INT := CASE WHEN TG_TABLE_NAME = 'transactions' THEN NEW.transaction_id ELSE OLD.transaction_id END;
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM Transactions t
+        WHERE t.transaction_id = target_transaction_id
+            AND t.transfer_id IS NULL
+            AND t.child_category_id IS NULL
+            AND NOT EXISTS (SELECT 1 FROM TransactionSplits s WHERE s.transaction_id = t.transaction_id)
+    ) THEN
+        RAISE EXCEPTION 'transaction % has neither a category nor splits', target_transaction_id
+            USING ERRCODE = 'check_violation', CONSTRAINT = 'transactions_category_check';
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER transactions_category_check
+    AFTER INSERT OR UPDATE ON Transactions
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_transaction_category();
+
+CREATE CONSTRAINT TRIGGER transactionsplits_category_check
+    AFTER UPDATE OR DELETE ON TransactionSplits
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_transaction_category();
@@ -0,0 +1,43 @@
+use axum::{
+    async_trait,
+    extract::{Extension, FromRequestParts},
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::token::decode_access_token;
+use crate::db::AppState;
+use crate::error::ApiError;
+
+// Authorization: Bearer <access token> から認証済みユーザーを取り出す
+pub struct AuthUser {
+    pub user_id: i32,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(app_state) = Extension::<Arc<Mutex<AppState>>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::internal("Missing application state", e))?;
+
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(ApiError::unauthorized)?;
+
+        let auth_config = app_state.lock().await.auth.clone();
+
+        match decode_access_token(token, &auth_config) {
+            Ok(claims) => Ok(AuthUser { user_id: claims.sub }),
+            Err(_) => Err(ApiError::unauthorized()),
+        }
+    }
+}
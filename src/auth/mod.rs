@@ -0,0 +1,4 @@
+pub mod extractor;
+pub mod ownership;
+pub mod password;
+pub mod token;
@@ -0,0 +1,180 @@
+use sqlx::{query_scalar, PgPool};
+use crate::error::ApiError;
+
+// 所有していないリソースは存在しないものとして扱い、ID の探索を防ぐ
+fn ownership_result(owned: Result<bool, sqlx::Error>) -> Result<(), ApiError> {
+    match owned {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::not_found()),
+        Err(e) => Err(ApiError::internal("Failed to check ownership", e)),
+    }
+}
+
+pub fn ensure_user(user_id: i32, auth_user_id: i32) -> Result<(), ApiError> {
+    if user_id == auth_user_id {
+        Ok(())
+    } else {
+        Err(ApiError::not_found())
+    }
+}
+
+pub async fn ensure_account_owner(db_pool: &PgPool, account_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM Accounts WHERE account_id = $1 AND user_id = $2) AS "owned!""#,
+            account_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_parent_category_owner(db_pool: &PgPool, parent_category_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM ParentCategories p
+                JOIN Accounts a ON a.account_id = p.account_id
+                WHERE p.parent_category_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            parent_category_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_child_category_owner(db_pool: &PgPool, child_category_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM ChildCategories c
+                JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+                JOIN Accounts a ON a.account_id = p.account_id
+                WHERE c.child_category_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            child_category_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_transaction_owner(db_pool: &PgPool, transaction_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM Transactions t
+                JOIN Accounts a ON a.account_id = t.account_id
+                WHERE t.transaction_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            transaction_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_budget_owner(db_pool: &PgPool, budget_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM Budgets WHERE budget_id = $1 AND user_id = $2) AS "owned!""#,
+            budget_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_budget_template_owner(db_pool: &PgPool, template_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM BudgetTemplates WHERE template_id = $1 AND user_id = $2) AS "owned!""#,
+            template_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_transfer_owner(db_pool: &PgPool, transfer_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM Transfers tr
+                JOIN Accounts a ON a.account_id = tr.from_account_id
+                WHERE tr.transfer_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            transfer_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_schedule_owner(db_pool: &PgPool, schedule_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM RecurringSchedules s
+                JOIN Accounts a ON a.account_id = s.account_id
+                WHERE s.schedule_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            schedule_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_import_mapping_owner(db_pool: &PgPool, mapping_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM ImportMappings m
+                JOIN Accounts a ON a.account_id = m.account_id
+                WHERE m.mapping_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            mapping_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_import_batch_owner(db_pool: &PgPool, import_batch_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM ImportBatches b
+                JOIN Accounts a ON a.account_id = b.account_id
+                WHERE b.import_batch_id = $1 AND a.user_id = $2
+            ) AS "owned!""#,
+            import_batch_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
+
+pub async fn ensure_notification_owner(db_pool: &PgPool, notification_id: i32, user_id: i32) -> Result<(), ApiError> {
+    ownership_result(
+        query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM Notifications WHERE notification_id = $1 AND user_id = $2) AS "owned!""#,
+            notification_id,
+            user_id
+        )
+        .fetch_one(db_pool)
+        .await,
+    )
+}
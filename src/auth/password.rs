@@ -0,0 +1,53 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use sqlx::{query, PgPool};
+
+// argon2id (デフォルトパラメータ) でハッシュ化し、PHC 文字列として返す
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default().hash_password(password.as_bytes(), &salt)?;
+    Ok(hash.to_string())
+}
+
+pub fn verify_password(password: &str, stored_hash: &str) -> bool {
+    match PasswordHash::new(stored_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+// 平文のまま保存されている既存ユーザーのパスワードをハッシュ化する。migrate hash-passwords で実行する
+pub async fn hash_plaintext_passwords(db_pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let users = query!(
+        "SELECT user_id, user_password FROM Users WHERE user_password NOT LIKE '$argon2%'"
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut hashed = 0;
+    for user in users {
+        let password_hash = match hash_password(&user.user_password) {
+            Ok(password_hash) => password_hash,
+            Err(e) => {
+                tracing::error!("Failed to hash password for user {}: {:?}", user.user_id, e);
+                continue;
+            }
+        };
+
+        hashed += query!(
+            "UPDATE Users SET user_password = $1 WHERE user_id = $2 AND user_password = $3",
+            password_hash,
+            user.user_id,
+            user.user_password
+        )
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(hashed)
+}
@@ -0,0 +1,78 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_ACCESS_TOKEN_TTL_SECONDS: i64 = 15 * 60;
+const DEFAULT_REFRESH_TOKEN_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+        let access_token_ttl = ttl_from_env("ACCESS_TOKEN_TTL_SECONDS", DEFAULT_ACCESS_TOKEN_TTL_SECONDS);
+        let refresh_token_ttl = ttl_from_env("REFRESH_TOKEN_TTL_SECONDS", DEFAULT_REFRESH_TOKEN_TTL_SECONDS);
+
+        AuthConfig { jwt_secret, access_token_ttl, refresh_token_ttl }
+    }
+}
+
+fn ttl_from_env(name: &str, default_seconds: i64) -> Duration {
+    let seconds = match std::env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| panic!("{} must be an integer", name)),
+        Err(_) => default_seconds,
+    };
+    Duration::seconds(seconds)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Claims {
+    pub sub: i32,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub fn issue_access_token(user_id: i32, config: &AuthConfig) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user_id,
+        iat: now.timestamp(),
+        exp: (now + config.access_token_ttl).timestamp(),
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(config.jwt_secret.as_bytes()))
+}
+
+pub fn decode_access_token(token: &str, config: &AuthConfig) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+        &Validation::default(),
+    )?;
+    Ok(data.claims)
+}
+
+// リフレッシュトークンはランダムな文字列とし、DB にはハッシュのみ保存する
+pub fn generate_refresh_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(REFRESH_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+pub fn hash_refresh_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub fn refresh_token_expires_at(config: &AuthConfig) -> NaiveDateTime {
+    (Utc::now() + config.refresh_token_ttl).naive_utc()
+}
@@ -0,0 +1,135 @@
+use chrono::{Local, NaiveDate};
+use sqlx::{query, query_as, query_scalar, PgPool};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use crate::models::budget_template::{BudgetPeriod, BudgetTemplate, Rollover};
+use crate::models::money::Money;
+
+const DEFAULT_GENERATE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+pub fn generate_interval_from_env() -> Duration {
+    let seconds = match std::env::var("BUDGET_GENERATE_INTERVAL_SECONDS") {
+        Ok(value) => value.parse().expect("BUDGET_GENERATE_INTERVAL_SECONDS must be an integer"),
+        Err(_) => DEFAULT_GENERATE_INTERVAL_SECONDS,
+    };
+    Duration::from_secs(seconds)
+}
+
+// テンプレートから期間ごとの予算を作成するタスクを一定間隔で繰り返す
+pub fn spawn_generator(db_pool: PgPool, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            let today = Local::now().date_naive();
+            match generate_due_budgets(&db_pool, today).await {
+                Ok(0) => {}
+                Ok(generated) => tracing::info!("Generated {} budgets from templates", generated),
+                Err(e) => tracing::error!("Failed to generate budgets from templates: {}", e),
+            }
+        }
+    })
+}
+
+// today までに始まった期間のうち、未作成の予算を作成して件数を返す
+pub async fn generate_due_budgets(db_pool: &PgPool, today: NaiveDate) -> Result<u64, sqlx::Error> {
+    let template_ids = query_scalar!(
+        "SELECT template_id FROM BudgetTemplates WHERE next_period_start <= $1 ORDER BY template_id",
+        today
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut generated = 0;
+    for template_id in template_ids {
+        // 1 件の失敗で他のテンプレートの処理を止めない
+        match generate_template_budgets(db_pool, template_id, today).await {
+            Ok(count) => generated += count,
+            Err(e) => tracing::error!("Failed to generate budgets for template {}: {}", template_id, e),
+        }
+    }
+
+    Ok(generated)
+}
+
+// 1 つのテンプレートについて today までに始まった期間の予算を古い順に作成する。
+// 繰越額は作成時点での前の期間の残額から決まり、後から前の期間に計上された取引は反映しない
+pub async fn generate_template_budgets(db_pool: &PgPool, template_id: i32, today: NaiveDate) -> Result<u64, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+
+    // 複数のインスタンスが同じテンプレートを同時に処理しないよう行をロックする
+    let template = query_as!(
+        BudgetTemplate,
+        r#"SELECT template_id, user_id, child_category_id, amount, period AS "period: BudgetPeriod", interval_count,
+            anchor_date, end_date, rollover AS "rollover: Rollover", generated_through
+        FROM BudgetTemplates
+        WHERE template_id = $1
+        FOR UPDATE SKIP LOCKED"#,
+        template_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(template) = template else {
+        return Ok(0);
+    };
+
+    let mut generated = 0;
+    let mut generated_through = template.generated_through;
+    for (start_date, end_date) in template.pending_periods().take_while(|(start_date, _)| *start_date <= today) {
+        let remaining = query_scalar!(
+            r#"SELECT p.remaining AS "remaining: Money"
+            FROM Budgets b
+            CROSS JOIN LATERAL budget_progress(b.budget_id, CURRENT_DATE) p
+            WHERE b.template_id = $1 AND b.start_date < $2
+            ORDER BY b.start_date DESC
+            LIMIT 1"#,
+            template_id,
+            start_date
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .flatten();
+
+        // 前の期間がない場合や、基準通貨に換算できない取引があって残額を求められない場合は繰り越さない
+        let rollover_amount = template.rollover.carry(remaining.unwrap_or_default());
+
+        // 一意制約により、同じ期間の予算が既にあれば何もしない
+        generated += query!(
+            "INSERT INTO Budgets (user_id, child_category_id, amount, start_date, end_date, template_id, rollover_amount)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (template_id, start_date) WHERE template_id IS NOT NULL DO NOTHING",
+            template.user_id,
+            template.child_category_id,
+            template.amount.as_decimal(),
+            start_date,
+            end_date,
+            template_id,
+            rollover_amount.as_decimal()
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        generated_through = Some(start_date);
+    }
+
+    // today より後に始まる最初の期間。end_date を過ぎて期間が残っていなければ None
+    let next_period_start = template
+        .pending_periods()
+        .map(|(start_date, _)| start_date)
+        .find(|start_date| *start_date > today);
+
+    query!(
+        "UPDATE BudgetTemplates SET generated_through = $1, next_period_start = $2 WHERE template_id = $3",
+        generated_through,
+        next_period_start,
+        template_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(generated)
+}
@@ -1,6 +1,8 @@
 use sqlx::PgPool;
+use crate::auth::token::AuthConfig;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: PgPool,
-}
\ No newline at end of file
+    pub auth: AuthConfig,
+}
@@ -0,0 +1,203 @@
+use axum::{
+    extract::Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::error::ErrorKind;
+use std::fmt;
+
+// API 全体で使うエラー型。クライアントには機械可読なコードとメッセージを返し、
+// 原因は tracing でログに残す
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    field: Option<String>,
+    message: String,
+    details: Vec<FieldError>,
+}
+
+// 入力検証で見つかった個々のフィールドのエラー
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: ErrorDetail<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorDetail<'a> {
+    code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    message: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    details: &'a [FieldError],
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError { status, code, field: None, message: message.into(), details: Vec::new() }
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    // ネストした入力 (アーカイブの取引など) を検証したエラーのフィールド名に親のフィールド名を付ける
+    pub fn in_field(mut self, parent: &str) -> Self {
+        let join = |field: &mut Option<String>| {
+            if let Some(field) = field {
+                *field = format!("{}.{}", parent, field);
+            }
+        };
+        join(&mut self.field);
+        for detail in &mut self.details {
+            join(&mut detail.field);
+        }
+        self
+    }
+
+    pub fn validation(details: Vec<FieldError>) -> Self {
+        let mut error = ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+            "the request contains invalid values",
+        );
+        error.details = details;
+        error
+    }
+
+    pub fn not_found() -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, "not_found", "resource not found")
+    }
+
+    pub fn unauthorized() -> Self {
+        ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", "authentication required")
+    }
+
+    pub fn invalid_reference(field: impl Into<String>) -> Self {
+        ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_reference",
+            "the referenced resource does not exist",
+        )
+        .with_field(field)
+    }
+
+    // リクエストボディで参照している ID が見つからない場合は 422 として返す
+    pub fn into_invalid_reference(self, field: impl Into<String>) -> Self {
+        if self.status == StatusCode::NOT_FOUND {
+            ApiError::invalid_reference(field)
+        } else {
+            self
+        }
+    }
+
+    pub fn internal(context: &str, cause: impl fmt::Debug) -> Self {
+        tracing::error!("{}: {:?}", context, cause);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "internal server error")
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.code, self.status, self.message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code: self.code,
+                field: self.field.as_deref(),
+                message: &self.message,
+                details: &self.details,
+            },
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+// 制約名 (例: transactions_child_category_id_fkey) から対象のカラム名を取り出す
+fn constraint_field(constraint: &str, table: Option<&str>) -> String {
+    let mut field = constraint;
+    if let Some(rest) = table.and_then(|table| field.strip_prefix(table)) {
+        field = rest.trim_start_matches('_');
+    }
+    for suffix in ["_fkey", "_key", "_check", "_pkey"] {
+        if let Some(rest) = field.strip_suffix(suffix) {
+            field = rest;
+            break;
+        }
+    }
+    field.to_string()
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        let db_err = match &err {
+            sqlx::Error::RowNotFound => return ApiError::not_found(),
+            sqlx::Error::Database(db_err) => db_err,
+            _ => return ApiError::internal("Database error", err),
+        };
+
+        let error = match db_err.kind() {
+            ErrorKind::UniqueViolation => ApiError::new(
+                StatusCode::CONFLICT,
+                "already_exists",
+                "a resource with the same value already exists",
+            ),
+            // 参照されている行を削除しようとした場合
+            ErrorKind::ForeignKeyViolation if db_err.message().starts_with("update or delete") => ApiError::new(
+                StatusCode::CONFLICT,
+                "still_referenced",
+                "the resource is still referenced by other resources",
+            ),
+            ErrorKind::ForeignKeyViolation => ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_reference",
+                "the referenced resource does not exist",
+            ),
+            ErrorKind::CheckViolation => ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_value",
+                "the value is not allowed",
+            ),
+            ErrorKind::NotNullViolation => ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "missing_value",
+                "a required value is missing",
+            ),
+            _ => match db_err.code().as_deref() {
+                Some("22001") => ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "value_too_long",
+                    "a value is too long",
+                ),
+                Some("22003") => ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "out_of_range",
+                    "a numeric value is out of range",
+                ),
+                _ => return ApiError::internal("Database error", err),
+            },
+        };
+
+        tracing::warn!("Database constraint violated: {}", db_err);
+
+        match db_err.constraint() {
+            Some(constraint) => error.with_field(constraint_field(constraint, db_err.table())),
+            None => error,
+        }
+    }
+}
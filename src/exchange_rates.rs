@@ -0,0 +1,43 @@
+use sqlx::{query, PgPool};
+use std::path::Path;
+use validator::Validate;
+use crate::models::exchange_rate::ExchangeRate;
+
+// 為替レートを登録する。同じ通貨の組と日付のレートがあれば上書きする
+pub async fn save_rates(db_pool: &PgPool, user_id: Option<i32>, rates: &[ExchangeRate]) -> Result<usize, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+
+    for rate in rates {
+        query!(
+            "INSERT INTO ExchangeRates (user_id, rate_date, base_currency, quote_currency, rate) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT ((COALESCE(user_id, 0)), base_currency, quote_currency, rate_date) DO UPDATE SET rate = EXCLUDED.rate",
+            user_id,
+            rate.rate_date,
+            rate.base_currency,
+            rate.quote_currency,
+            rate.rate
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(rates.len())
+}
+
+// rate_date,base_currency,quote_currency,rate の見出し行を持つ CSV ファイルから共通のレートを読み込む
+pub async fn load_file(db_pool: &PgPool, path: impl AsRef<Path>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let mut reader = csv::Reader::from_path(path)?;
+
+    let mut rates = Vec::new();
+    for (index, record) in reader.deserialize::<ExchangeRate>().enumerate() {
+        // 見出し行が 1 行目なのでデータは 2 行目から
+        let line = index + 2;
+        let rate = record.map_err(|e| format!("line {}: {}", line, e))?;
+        rate.validate().map_err(|e| format!("line {}: {}", line, e))?;
+        rates.push(rate);
+    }
+
+    Ok(save_rates(db_pool, None, &rates).await?)
+}
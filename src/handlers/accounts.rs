@@ -3,88 +3,202 @@ use axum::{
     response::IntoResponse,
     http::StatusCode,
 };
-use sqlx::{query_as, query};
+use sqlx::{query_as, query, query_scalar, PgExecutor, PgPool};
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::ensure_account_owner;
 use crate::db::AppState;
-use crate::models::account::Account;
+use crate::error::ApiError;
+use crate::models::account::{Account, BalanceHistoryQuery, BalancePoint};
+use crate::models::money::{InCurrency, Money};
+use crate::validation::{validate_minor_units, ValidatedJson, ValidatedQuery};
+
+// 取引などの金額は口座の通貨で保存しているため、使われている口座の通貨は変更できない。変更後の通貨を返す
+// 口座の金額の通貨
+pub(crate) async fn fetch_account_currency<'e>(executor: impl PgExecutor<'e>, account_id: i32) -> Result<String, ApiError> {
+    let currency = query_scalar!("SELECT currency FROM Accounts WHERE account_id = $1", account_id)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(currency)
+}
+
+async fn ensure_currency_change(db_pool: &PgPool, account_id: i32, currency: Option<&str>) -> Result<String, ApiError> {
+    let current = query!(
+        r#"SELECT a.currency,
+            EXISTS(SELECT 1 FROM Transactions t WHERE t.account_id = a.account_id)
+            OR EXISTS(SELECT 1 FROM Transfers tr WHERE a.account_id IN (tr.from_account_id, tr.to_account_id))
+            OR EXISTS(SELECT 1 FROM RecurringSchedules s WHERE s.account_id = a.account_id) AS "in_use!"
+        FROM Accounts a
+        WHERE a.account_id = $1"#,
+        account_id
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    match currency {
+        None => Ok(current.currency),
+        Some(currency) if currency == current.currency || !current.in_use => Ok(currency.to_string()),
+        Some(_) => Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "account_in_use",
+            "the currency cannot be changed while transactions, transfers or recurring schedules use the account",
+        ).with_field("currency")),
+    }
+}
 
 pub async fn create_account(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
-    Json(account): Json<Account>
-) -> impl IntoResponse {
+    auth_user: AuthUser,
+    ValidatedJson(account): ValidatedJson<Account>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    let currency = match account.currency.clone() {
+        Some(currency) => currency,
+        None => query_scalar!("SELECT base_currency FROM Users WHERE user_id = $1", auth_user.user_id)
+            .fetch_one(&db_pool)
+            .await?,
+    };
+    validate_minor_units(&currency, [("initial_balance".to_string(), &account.initial_balance)])?;
+
+    let new_account = query_as!(
         Account,
-        "INSERT INTO Accounts (user_id, account_name, initial_balance) VALUES ($1, $2, $3) RETURNING account_id, user_id, account_name, initial_balance, created_at",
-        account.user_id,
+        r#"INSERT INTO Accounts (user_id, account_name, initial_balance, currency) VALUES ($1, $2, $3, $4)
+        RETURNING account_id, user_id, account_name, initial_balance, currency, created_at, initial_balance AS "current_balance: Money""#,
+        auth_user.user_id,
         account.account_name,
-        account.initial_balance
+        account.initial_balance.as_decimal(),
+        currency
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(new_account) => (StatusCode::CREATED, Json(new_account)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(new_account.in_currency(&currency))))
 }
 
 pub async fn get_account(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(account_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let account = query_as!(
         Account,
-        "SELECT account_id, user_id, account_name, initial_balance, created_at FROM Accounts WHERE account_id = $1",
+        r#"SELECT account_id, user_id, account_name, initial_balance, currency, created_at, account_balance(account_id) AS "current_balance: Money" FROM Accounts WHERE account_id = $1"#,
         account_id
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(account) => (StatusCode::OK, Json(account)).into_response(),
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
-    }
+    .await?;
+    let currency = account.currency.clone().unwrap_or_default();
+
+    Ok((StatusCode::OK, Json(account.in_currency(&currency))))
 }
 
 pub async fn update_account(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(account_id): Path<i32>,
-    Json(account): Json<Account>
-) -> impl IntoResponse {
+    ValidatedJson(account): ValidatedJson<Account>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let currency = ensure_currency_change(&db_pool, account_id, account.currency.as_deref()).await?;
+    validate_minor_units(&currency, [("initial_balance".to_string(), &account.initial_balance)])?;
+
+    let updated_account = query_as!(
         Account,
-        "UPDATE Accounts SET account_name = $1, initial_balance = $2 WHERE account_id = $3 RETURNING account_id, user_id, account_name, initial_balance, created_at",
+        r#"UPDATE Accounts SET account_name = $1, initial_balance = $2, currency = $3 WHERE account_id = $4
+        RETURNING account_id, user_id, account_name, initial_balance, currency, created_at, account_balance(account_id) AS "current_balance: Money""#,
         account.account_name,
-        account.initial_balance,
+        account.initial_balance.as_decimal(),
+        currency,
         account_id
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(updated_account) => (StatusCode::OK, Json(updated_account)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::OK, Json(updated_account.in_currency(&currency))))
 }
 
 pub async fn delete_account(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(account_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query!(
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    query!(
         "DELETE FROM Accounts WHERE account_id = $1",
         account_id
     )
     .execute(&db_pool)
-    .await
-    {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_balance_history(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<BalanceHistoryQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    // 期間ごとの増減を集計し、ウィンドウ関数で累積して各期間末の残高を求める。
+    // 取引のない期間も返すため generate_series で期間を補完する
+    let history = query_as!(
+        BalancePoint,
+        r#"WITH periods AS (
+            SELECT generate_series(
+                date_trunc($2, $3::date::timestamp),
+                date_trunc($2, $4::date::timestamp),
+                ('1 ' || $2)::interval
+            )::date AS period_start
+        ),
+        flows AS (
+            SELECT date_trunc($2, transaction_date::timestamp)::date AS period_start,
+                SUM(CASE WHEN transaction_type = 1 THEN 1 ELSE -1 END
+                    * account_amount(transaction_amount, currency, transaction_date, account_id)) AS net_change
+            FROM Transactions
+            WHERE account_id = $1 AND transaction_date <= $4
+            GROUP BY 1
+            UNION ALL
+            SELECT period_start, 0 FROM periods
+        ),
+        running AS (
+            SELECT period_start,
+                SUM(net_change) AS net_change,
+                SUM(SUM(net_change)) OVER (ORDER BY period_start) AS cumulative_change
+            FROM flows
+            GROUP BY period_start
+        )
+        SELECT r.period_start AS "period_start!",
+            r.net_change AS "net_change!",
+            a.initial_balance + r.cumulative_change AS "balance!"
+        FROM running r
+        CROSS JOIN Accounts a
+        WHERE a.account_id = $1 AND r.period_start >= date_trunc($2, $3::date::timestamp)::date
+        ORDER BY r.period_start"#,
+        account_id,
+        params.granularity.as_str(),
+        params.from,
+        params.to
+    )
+    .fetch_all(&db_pool)
+    .await?;
+    let currency = fetch_account_currency(&db_pool, account_id).await?;
+
+    Ok((StatusCode::OK, Json(history.in_currency(&currency))))
 }
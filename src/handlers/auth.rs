@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Json, Extension},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use chrono::Utc;
+use sqlx::{query, PgExecutor};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::password::verify_password;
+use crate::auth::token::{
+    generate_refresh_token, hash_refresh_token, issue_access_token, refresh_token_expires_at, AuthConfig,
+};
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::models::auth::{LoginRequest, RefreshRequest, TokenResponse};
+
+// アクセストークンを発行し、リフレッシュトークンを保存する
+async fn issue_tokens<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: i32,
+    auth_config: &AuthConfig,
+) -> Result<TokenResponse, ApiError> {
+    let access_token = issue_access_token(user_id, auth_config)
+        .map_err(|e| ApiError::internal("Failed to issue access token", e))?;
+    let refresh_token = generate_refresh_token();
+
+    query!(
+        "INSERT INTO RefreshTokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        user_id,
+        hash_refresh_token(&refresh_token),
+        refresh_token_expires_at(auth_config)
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(TokenResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer",
+        expires_in: auth_config.access_token_ttl.num_seconds(),
+    })
+}
+
+pub async fn login(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    Json(credentials): Json<LoginRequest>
+) -> Result<impl IntoResponse, ApiError> {
+    let (db_pool, auth_config) = {
+        let state = state.lock().await;
+        (state.db_pool.clone(), state.auth.clone())
+    };
+
+    let user = query!(
+        "SELECT user_id, user_password FROM Users WHERE user_email = $1",
+        credentials.user_email
+    )
+    .fetch_optional(&db_pool)
+    .await?
+    .ok_or_else(ApiError::unauthorized)?;
+
+    if !verify_password(&credentials.user_password, &user.user_password) {
+        return Err(ApiError::unauthorized());
+    }
+
+    let tokens = issue_tokens(&db_pool, user.user_id, &auth_config).await?;
+
+    Ok((StatusCode::OK, Json(tokens)))
+}
+
+pub async fn refresh(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    Json(request): Json<RefreshRequest>
+) -> Result<impl IntoResponse, ApiError> {
+    let (db_pool, auth_config) = {
+        let state = state.lock().await;
+        (state.db_pool.clone(), state.auth.clone())
+    };
+
+    let mut tx = db_pool.begin().await?;
+
+    // 使用したリフレッシュトークンは失効させ、新しいものと交換する
+    let revoked = query!(
+        "UPDATE RefreshTokens SET revoked_at = $1 WHERE token_hash = $2 AND revoked_at IS NULL AND expires_at > $1 RETURNING user_id",
+        Utc::now().naive_utc(),
+        hash_refresh_token(&request.refresh_token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(ApiError::unauthorized)?;
+
+    let tokens = issue_tokens(&mut *tx, revoked.user_id, &auth_config).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(tokens)))
+}
+
+pub async fn logout(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Json(request): Json<RefreshRequest>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    query!(
+        "UPDATE RefreshTokens SET revoked_at = $1 WHERE token_hash = $2 AND user_id = $3 AND revoked_at IS NULL",
+        Utc::now().naive_utc(),
+        hash_refresh_token(&request.refresh_token),
+        auth_user.user_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Json, Extension, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use chrono::Local;
+use sqlx::{query_as, query, query_scalar, PgExecutor};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_budget_template_owner, ensure_child_category_owner, ensure_user};
+use crate::budget_templates::generate_template_budgets;
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::handlers::users::fetch_base_currency;
+use crate::models::budget_template::{BudgetPeriod, BudgetTemplate, Rollover};
+use crate::models::money::InCurrency;
+use crate::validation::{validate_minor_units, ValidatedJson};
+
+async fn fetch_template<'e>(executor: impl PgExecutor<'e>, template_id: i32) -> Result<BudgetTemplate, ApiError> {
+    let template = query_as!(
+        BudgetTemplate,
+        r#"SELECT template_id, user_id, child_category_id, amount, period AS "period: BudgetPeriod", interval_count,
+            anchor_date, end_date, rollover AS "rollover: Rollover", generated_through
+        FROM BudgetTemplates
+        WHERE template_id = $1"#,
+        template_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(template)
+}
+
+// 作成と同時に、今日までに始まった期間の予算を作成する
+pub async fn create_budget_template(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedJson(template): ValidatedJson<BudgetTemplate>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_child_category_owner(&db_pool, template.child_category_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("child_category_id"))?;
+
+    let currency = fetch_base_currency(&db_pool, auth_user.user_id).await?;
+    validate_minor_units(&currency, [("amount".to_string(), &template.amount)])?;
+
+    let template_id = query_scalar!(
+        "INSERT INTO BudgetTemplates (user_id, child_category_id, amount, period, interval_count, anchor_date, end_date, rollover, next_period_start)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $6) RETURNING template_id",
+        auth_user.user_id,
+        template.child_category_id,
+        template.amount.as_decimal(),
+        template.period as BudgetPeriod,
+        template.interval_count,
+        template.anchor_date,
+        template.end_date,
+        template.rollover as Rollover
+    )
+    .fetch_one(&db_pool)
+    .await?;
+
+    generate_template_budgets(&db_pool, template_id, Local::now().date_naive()).await?;
+
+    let new_template = fetch_template(&db_pool, template_id).await?;
+
+    Ok((StatusCode::CREATED, Json(new_template.in_currency(&currency))))
+}
+
+pub async fn get_budget_template(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(template_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_budget_template_owner(&db_pool, template_id, auth_user.user_id).await?;
+
+    let template = fetch_template(&db_pool, template_id).await?;
+    let currency = fetch_base_currency(&db_pool, auth_user.user_id).await?;
+
+    Ok((StatusCode::OK, Json(template.in_currency(&currency))))
+}
+
+pub async fn list_user_budget_templates(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(user_id, auth_user.user_id)?;
+
+    let templates = query_as!(
+        BudgetTemplate,
+        r#"SELECT template_id, user_id, child_category_id, amount, period AS "period: BudgetPeriod", interval_count,
+            anchor_date, end_date, rollover AS "rollover: Rollover", generated_through
+        FROM BudgetTemplates
+        WHERE user_id = $1
+        ORDER BY template_id"#,
+        user_id
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let currency = fetch_base_currency(&db_pool, user_id).await?;
+
+    Ok((StatusCode::OK, Json(templates.in_currency(&currency))))
+}
+
+// 作成済みの予算はそのまま残し、以降に作成する期間に新しい内容を適用する
+pub async fn update_budget_template(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(template_id): Path<i32>,
+    ValidatedJson(template): ValidatedJson<BudgetTemplate>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_budget_template_owner(&db_pool, template_id, auth_user.user_id).await?;
+    ensure_child_category_owner(&db_pool, template.child_category_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("child_category_id"))?;
+
+    let currency = fetch_base_currency(&db_pool, auth_user.user_id).await?;
+    validate_minor_units(&currency, [("amount".to_string(), &template.amount)])?;
+
+    query!(
+        "UPDATE BudgetTemplates SET child_category_id = $1, amount = $2, period = $3, interval_count = $4, anchor_date = $5, end_date = $6, rollover = $7,
+            next_period_start = $5
+        WHERE template_id = $8",
+        template.child_category_id,
+        template.amount.as_decimal(),
+        template.period as BudgetPeriod,
+        template.interval_count,
+        template.anchor_date,
+        template.end_date,
+        template.rollover as Rollover,
+        template_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    generate_template_budgets(&db_pool, template_id, Local::now().date_naive()).await?;
+
+    let updated_template = fetch_template(&db_pool, template_id).await?;
+
+    Ok((StatusCode::OK, Json(updated_template.in_currency(&currency))))
+}
+
+pub async fn delete_budget_template(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(template_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_budget_template_owner(&db_pool, template_id, auth_user.user_id).await?;
+
+    // 作成済みの予算は ON DELETE SET NULL で通常の予算として残る
+    query!(
+        "DELETE FROM BudgetTemplates WHERE template_id = $1",
+        template_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -3,91 +3,247 @@ use axum::{
     response::IntoResponse,
     http::StatusCode,
 };
-use sqlx::{query_as, query};
+use chrono::NaiveDate;
+use sqlx::{query_as, query, query_scalar, PgExecutor};
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_budget_owner, ensure_child_category_owner, ensure_user};
 use crate::db::AppState;
-use crate::models::budget::Budget;
+use crate::error::ApiError;
+use crate::models::budget::{Budget, BudgetGroup, BudgetOverview, BudgetOverviewQuery, UnbudgetedCategory, UnbudgetedSpending};
+use crate::handlers::exchange_rates::ensure_base_currency_rates;
+use crate::handlers::users::fetch_base_currency;
+use crate::models::money::{InCurrency, Money};
+use crate::models::period::Month;
+use crate::validation::{validate_minor_units, ValidatedJson, ValidatedQuery};
+
+async fn fetch_budget<'e>(executor: impl PgExecutor<'e>, budget_id: i32) -> Result<Budget, ApiError> {
+    let budget = query_as!(
+        Budget,
+        r#"SELECT b.budget_id, b.user_id, b.child_category_id, b.amount, b.start_date, b.end_date, b.rollover_amount, b.template_id, b.alert_thresholds, p.spent AS "spent: Money", p.remaining AS "remaining: Money", p.percent_used, p.projected_spending AS "projected_spending: Money"
+        FROM Budgets b
+        CROSS JOIN LATERAL budget_progress(b.budget_id, CURRENT_DATE) p
+        WHERE b.budget_id = $1"#,
+        budget_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(budget)
+}
 
 pub async fn create_budget(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
-    Json(budget): Json<Budget>
-) -> impl IntoResponse {
+    auth_user: AuthUser,
+    ValidatedJson(budget): ValidatedJson<Budget>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
-        Budget,
-        "INSERT INTO Budgets (user_id, child_category_id, amount, start_date, end_date) VALUES ($1, $2, $3, $4, $5) RETURNING budget_id, user_id, child_category_id, amount, start_date, end_date",
-        budget.user_id,
+    ensure_child_category_owner(&db_pool, budget.child_category_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("child_category_id"))?;
+    let currency = fetch_base_currency(&db_pool, auth_user.user_id).await?;
+    ensure_base_currency_units(&currency, &budget)?;
+
+    let budget_id = query_scalar!(
+        "INSERT INTO Budgets (user_id, child_category_id, amount, start_date, end_date, rollover_amount, alert_thresholds) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING budget_id",
+        auth_user.user_id,
         budget.child_category_id,
-        budget.amount,
+        budget.amount.as_decimal(),
         budget.start_date,
-        budget.end_date
+        budget.end_date,
+        budget.rollover_amount.as_decimal(),
+        &budget.alert_thresholds
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(new_budget) => (StatusCode::CREATED, Json(new_budget)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    let new_budget = fetch_budget(&db_pool, budget_id).await?;
+
+    Ok((StatusCode::CREATED, Json(new_budget.in_currency(&currency))))
 }
 
 pub async fn get_budget(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(budget_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
-        Budget,
-        "SELECT budget_id, user_id, child_category_id, amount, start_date, end_date FROM Budgets WHERE budget_id = $1",
-        budget_id
-    )
-    .fetch_one(&db_pool)
-    .await
-    {
-        Ok(budget) => (StatusCode::OK, Json(budget)).into_response(),
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
-    }
+    ensure_budget_owner(&db_pool, budget_id, auth_user.user_id).await?;
+
+    let budget = fetch_budget(&db_pool, budget_id).await?;
+    let currency = ensure_base_currency_rates(&db_pool, auth_user.user_id, budget.start_date, budget.end_date, None).await?;
+
+    Ok((StatusCode::OK, Json(budget.in_currency(&currency))))
 }
 
 pub async fn update_budget(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(budget_id): Path<i32>,
-    Json(budget): Json<Budget>
-) -> impl IntoResponse {
+    ValidatedJson(budget): ValidatedJson<Budget>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
-        Budget,
-        "UPDATE Budgets SET amount = $1, start_date = $2, end_date = $3 WHERE budget_id = $4 RETURNING budget_id, user_id, child_category_id, amount, start_date, end_date",
-        budget.amount,
+    ensure_budget_owner(&db_pool, budget_id, auth_user.user_id).await?;
+    let currency = fetch_base_currency(&db_pool, auth_user.user_id).await?;
+    ensure_base_currency_units(&currency, &budget)?;
+
+    query!(
+        "UPDATE Budgets SET amount = $1, start_date = $2, end_date = $3, rollover_amount = $4, alert_thresholds = $5 WHERE budget_id = $6",
+        budget.amount.as_decimal(),
         budget.start_date,
         budget.end_date,
+        budget.rollover_amount.as_decimal(),
+        &budget.alert_thresholds,
         budget_id
     )
-    .fetch_one(&db_pool)
-    .await
-    {
-        Ok(updated_budget) => (StatusCode::OK, Json(updated_budget)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .execute(&db_pool)
+    .await?;
+
+    let updated_budget = fetch_budget(&db_pool, budget_id).await?;
+
+    Ok((StatusCode::OK, Json(updated_budget.in_currency(&currency))))
 }
 
 pub async fn delete_budget(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(budget_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query!(
+    ensure_budget_owner(&db_pool, budget_id, auth_user.user_id).await?;
+
+    query!(
         "DELETE FROM Budgets WHERE budget_id = $1",
         budget_id
     )
     .execute(&db_pool)
-    .await
-    {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 予算の金額はユーザーの基準通貨で扱う
+pub(crate) fn ensure_base_currency_units(currency: &str, budget: &Budget) -> Result<(), ApiError> {
+    validate_minor_units(currency, [
+        ("amount".to_string(), &budget.amount),
+        ("rollover_amount".to_string(), &budget.rollover_amount),
+    ])
+}
+
+// 期間と重なる予算を親カテゴリごとにまとめ、予算のないカテゴリへの支出も合わせて返す。
+// 各予算の消化状況は予算自体の期間で集計する。支出は予算と同じく基準通貨に換算する
+pub async fn get_user_budgets(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(user_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<BudgetOverviewQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(user_id, auth_user.user_id)?;
+
+    let period = params.period.unwrap_or_else(Month::current);
+    let (period_start, period_end) = (period.first_day(), period.last_day());
+
+    let rows = query!(
+        r#"SELECT b.budget_id, b.user_id, b.child_category_id, b.amount AS "amount: Money", b.start_date, b.end_date, b.rollover_amount AS "rollover_amount: Money", b.template_id, b.alert_thresholds, pr.spent AS "spent: Money", pr.remaining AS "remaining: Money", pr.percent_used, pr.projected_spending AS "projected_spending: Money",
+            p.parent_category_id, p.parent_category_name
+        FROM Budgets b
+        JOIN ChildCategories c ON c.child_category_id = b.child_category_id
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        CROSS JOIN LATERAL budget_progress(b.budget_id, CURRENT_DATE) pr
+        WHERE b.user_id = $1 AND b.start_date <= $3 AND b.end_date >= $2
+        ORDER BY p.parent_category_name, p.parent_category_id, c.child_category_name, b.start_date"#,
+        user_id,
+        period_start,
+        period_end
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    // 予算の消化状況は予算自体の期間で集計するため、その期間の取引も換算できることを確かめる
+    let from = rows.iter().map(|row| row.start_date).fold(period_start, NaiveDate::min);
+    let to = rows.iter().map(|row| row.end_date).fold(period_end, NaiveDate::max);
+    let currency = ensure_base_currency_rates(&db_pool, user_id, from, to, None).await?;
+
+    let mut groups: Vec<BudgetGroup> = Vec::new();
+    for row in rows {
+        let budget = Budget {
+            budget_id: Some(row.budget_id),
+            user_id: row.user_id,
+            child_category_id: row.child_category_id,
+            amount: row.amount,
+            start_date: row.start_date,
+            end_date: row.end_date,
+            rollover_amount: row.rollover_amount,
+            template_id: row.template_id,
+            alert_thresholds: row.alert_thresholds,
+            spent: row.spent,
+            remaining: row.remaining,
+            percent_used: row.percent_used,
+            projected_spending: row.projected_spending,
+        };
+
+        if groups.last().map(|group| group.parent_category_id) != Some(row.parent_category_id) {
+            groups.push(BudgetGroup {
+                parent_category_id: row.parent_category_id,
+                parent_category_name: row.parent_category_name,
+                budgeted: Money::zero(),
+                spent: Money::zero(),
+                remaining: Money::zero(),
+                budgets: Vec::new(),
+            });
+        }
+        if let Some(group) = groups.last_mut() {
+            group.budgeted += &budget.amount + &budget.rollover_amount;
+            group.spent += budget.spent.clone().unwrap_or_default();
+            group.remaining = &group.budgeted - &group.spent;
+            group.budgets.push(budget);
+        }
     }
+
+    let categories = query_as!(
+        UnbudgetedCategory,
+        r#"SELECT c.child_category_id, c.child_category_name, p.parent_category_id, p.parent_category_name,
+            SUM(base_amount(l.amount, l.currency, l.transaction_date, a.user_id)) AS "spent!"
+        FROM TransactionCategoryLines l
+        JOIN Accounts a ON a.account_id = l.account_id
+        JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        WHERE a.user_id = $1
+            AND l.transaction_type = 2
+            AND l.transaction_date BETWEEN $2 AND $3
+            AND NOT EXISTS (
+                SELECT 1 FROM Budgets b
+                WHERE b.user_id = $1 AND b.child_category_id = l.child_category_id AND b.start_date <= $3 AND b.end_date >= $2
+            )
+        GROUP BY c.child_category_id, c.child_category_name, p.parent_category_id, p.parent_category_name
+        ORDER BY SUM(base_amount(l.amount, l.currency, l.transaction_date, a.user_id)) DESC, c.child_category_id"#,
+        user_id,
+        period_start,
+        period_end
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let budgeted: Money = groups.iter().map(|group| &group.budgeted).sum();
+    let spent: Money = groups.iter().map(|group| &group.spent).sum();
+
+    Ok((StatusCode::OK, Json(BudgetOverview {
+        period_start,
+        period_end,
+        remaining: &budgeted - &spent,
+        budgeted,
+        spent,
+        groups,
+        unbudgeted: UnbudgetedSpending {
+            spent: categories.iter().map(|category| &category.spent).sum(),
+            categories,
+        },
+    }.in_currency(&currency))))
 }
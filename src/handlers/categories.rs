@@ -3,164 +3,205 @@ use axum::{
     response::IntoResponse,
     http::StatusCode,
 };
-use sqlx::{query_as, query};
+use sqlx::{query_as, query, PgPool};
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_account_owner, ensure_child_category_owner, ensure_parent_category_owner};
 use crate::db::AppState;
-use crate::models::{parent_category::ParentCategory, child_category::ChildCategory};
+use crate::error::ApiError;
+use crate::models::{parent_category::{CategoryType, ParentCategory}, child_category::ChildCategory};
+use crate::validation::ValidatedJson;
+
+// 取引と定期取引の種別はカテゴリの種別に合わせているため、使われているカテゴリの種別は変更できない
+async fn ensure_category_type_unchanged(db_pool: &PgPool, parent_category_id: i32, category_type: CategoryType) -> Result<(), ApiError> {
+    let current = query!(
+        r#"SELECT p.category_type AS "category_type: CategoryType",
+            EXISTS(
+                SELECT 1 FROM TransactionCategoryLines l
+                JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+                WHERE c.parent_category_id = p.parent_category_id
+            ) OR EXISTS(
+                SELECT 1 FROM RecurringSchedules s
+                JOIN ChildCategories c ON c.child_category_id = s.child_category_id
+                WHERE c.parent_category_id = p.parent_category_id
+            ) AS "in_use!"
+        FROM ParentCategories p
+        WHERE p.parent_category_id = $1"#,
+        parent_category_id
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    if current.category_type == category_type || !current.in_use {
+        return Ok(());
+    }
+
+    Err(ApiError::new(
+        StatusCode::CONFLICT,
+        "category_in_use",
+        "the category type cannot be changed while transactions or recurring schedules use the category",
+    ).with_field("category_type"))
+}
 
 pub async fn create_parent_category(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
-    Json(category): Json<ParentCategory>
-) -> impl IntoResponse {
+    auth_user: AuthUser,
+    ValidatedJson(category): ValidatedJson<ParentCategory>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_account_owner(&db_pool, category.account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("account_id"))?;
+
+    let new_category = query_as!(
         ParentCategory,
-        "INSERT INTO ParentCategories (account_id, parent_category_name, color, category_type) VALUES ($1, $2, $3, $4) RETURNING parent_category_id, account_id, parent_category_name, color, category_type",
+        r#"INSERT INTO ParentCategories (account_id, parent_category_name, color, category_type) VALUES ($1, $2, $3, $4) RETURNING parent_category_id, account_id, parent_category_name, color, category_type AS "category_type: CategoryType""#,
         category.account_id,
         category.parent_category_name,
         category.color,
         category.category_type as i32
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(new_category) => (StatusCode::CREATED, Json(new_category)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(new_category)))
 }
 
 pub async fn create_child_category(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
-    Json(category): Json<ChildCategory>
-) -> impl IntoResponse {
+    auth_user: AuthUser,
+    ValidatedJson(category): ValidatedJson<ChildCategory>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_parent_category_owner(&db_pool, category.parent_category_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("parent_category_id"))?;
+
+    let new_category = query_as!(
         ChildCategory,
         "INSERT INTO ChildCategories (parent_category_id, child_category_name) VALUES ($1, $2) RETURNING child_category_id, parent_category_id, child_category_name",
         category.parent_category_id,
         category.child_category_name
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(new_category) => (StatusCode::CREATED, Json(new_category)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(new_category)))
 }
 
 pub async fn get_categories(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(account_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    let parent_categories: Vec<ParentCategory> = match query_as!(
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let parent_categories: Vec<ParentCategory> = query_as!(
         ParentCategory,
-        "SELECT parent_category_id, account_id, parent_category_name, color, category_type FROM ParentCategories WHERE account_id = $1",
+        r#"SELECT parent_category_id, account_id, parent_category_name, color, category_type AS "category_type: CategoryType" FROM ParentCategories WHERE account_id = $1"#,
         account_id
     )
     .fetch_all(&db_pool)
-    .await
-    {
-        Ok(categories) => categories,
-        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    };
+    .await?;
 
-    let child_categories: Vec<ChildCategory> = match query_as!(
+    let child_categories: Vec<ChildCategory> = query_as!(
         ChildCategory,
         "SELECT child_category_id, parent_category_id, child_category_name FROM ChildCategories WHERE parent_category_id IN (SELECT parent_category_id FROM ParentCategories WHERE account_id = $1)",
         account_id
     )
     .fetch_all(&db_pool)
-    .await
-    {
-        Ok(categories) => categories,
-        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    };
+    .await?;
 
     let response = (parent_categories, child_categories);
-    (StatusCode::OK, Json(response)).into_response()
+    Ok((StatusCode::OK, Json(response)))
 }
 
 pub async fn update_parent_category(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(parent_category_id): Path<i32>,
-    Json(category): Json<ParentCategory>
-) -> impl IntoResponse {
+    ValidatedJson(category): ValidatedJson<ParentCategory>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_parent_category_owner(&db_pool, parent_category_id, auth_user.user_id).await?;
+    ensure_category_type_unchanged(&db_pool, parent_category_id, category.category_type).await?;
+
+    let updated_category = query_as!(
         ParentCategory,
-        "UPDATE ParentCategories SET parent_category_name = $1, color = $2, category_type = $3 WHERE parent_category_id = $4 RETURNING parent_category_id, account_id, parent_category_name, color, category_type",
+        r#"UPDATE ParentCategories SET parent_category_name = $1, color = $2, category_type = $3 WHERE parent_category_id = $4 RETURNING parent_category_id, account_id, parent_category_name, color, category_type AS "category_type: CategoryType""#,
         category.parent_category_name,
         category.color,
         category.category_type as i32,
         parent_category_id
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(updated_category) => (StatusCode::OK, Json(updated_category)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::OK, Json(updated_category)))
 }
 
 pub async fn update_child_category(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(child_category_id): Path<i32>,
-    Json(category): Json<ChildCategory>
-) -> impl IntoResponse {
+    ValidatedJson(category): ValidatedJson<ChildCategory>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_child_category_owner(&db_pool, child_category_id, auth_user.user_id).await?;
+
+    let updated_category = query_as!(
         ChildCategory,
         "UPDATE ChildCategories SET child_category_name = $1 WHERE child_category_id = $2 RETURNING child_category_id, parent_category_id, child_category_name",
         category.child_category_name,
         child_category_id
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(updated_category) => (StatusCode::OK, Json(updated_category)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::OK, Json(updated_category)))
 }
 
 pub async fn delete_parent_category(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(parent_category_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query!(
+    ensure_parent_category_owner(&db_pool, parent_category_id, auth_user.user_id).await?;
+
+    query!(
         "DELETE FROM ParentCategories WHERE parent_category_id = $1",
         parent_category_id
     )
     .execute(&db_pool)
-    .await
-    {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn delete_child_category(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(child_category_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query!(
+    ensure_child_category_owner(&db_pool, child_category_id, auth_user.user_id).await?;
+
+    query!(
         "DELETE FROM ChildCategories WHERE child_category_id = $1",
         child_category_id
     )
     .execute(&db_pool)
-    .await
-    {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,104 @@
+use axum::{
+    extract::{Json, Extension},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use chrono::NaiveDate;
+use sqlx::{query, query_as, PgPool};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::exchange_rates::save_rates;
+use crate::handlers::users::fetch_base_currency;
+use crate::models::exchange_rate::{ExchangeRate, ExchangeRateQuery, ExchangeRateUpload, ExchangeRateUploadResult};
+use crate::validation::{ValidatedJson, ValidatedQuery};
+
+// 期間内の取引を利用者の基準通貨に換算できることを確かめ、基準通貨を返す。
+// 換算できない通貨の取引があれば、どのレートが足りないかを返す
+pub(crate) async fn ensure_base_currency_rates(
+    db_pool: &PgPool,
+    user_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+    account_id: Option<i32>,
+) -> Result<String, ApiError> {
+    let base_currency = fetch_base_currency(db_pool, user_id).await?;
+
+    let missing = query!(
+        r#"SELECT l.currency AS "currency!", MIN(l.transaction_date) AS "transaction_date!"
+        FROM TransactionCategoryLines l
+        JOIN Accounts a ON a.account_id = l.account_id
+        WHERE a.user_id = $1
+            AND l.transaction_date BETWEEN $2 AND $3
+            AND ($4::int IS NULL OR l.account_id = $4)
+            AND exchange_rate(l.currency, $5, l.transaction_date, $1) IS NULL
+        GROUP BY l.currency
+        ORDER BY l.currency
+        LIMIT 1"#,
+        user_id,
+        from,
+        to,
+        account_id,
+        base_currency
+    )
+    .fetch_optional(db_pool)
+    .await?;
+
+    match missing {
+        Some(missing) => Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "missing_exchange_rate",
+            format!(
+                "no exchange rate from {} to {} on or before {}",
+                missing.currency, base_currency, missing.transaction_date
+            ),
+        )),
+        None => Ok(base_currency),
+    }
+}
+
+// 共通のレートと認証済みユーザーのレートを返す
+pub async fn list_exchange_rates(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedQuery(params): ValidatedQuery<ExchangeRateQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    let rates = query_as!(
+        ExchangeRate,
+        r#"SELECT exchange_rate_id, user_id, rate_date, base_currency, quote_currency, rate
+        FROM ExchangeRates
+        WHERE (user_id IS NULL OR user_id = $1)
+            AND ($2::text IS NULL OR base_currency = $2)
+            AND ($3::text IS NULL OR quote_currency = $3)
+            AND ($4::date IS NULL OR rate_date >= $4)
+            AND ($5::date IS NULL OR rate_date <= $5)
+        ORDER BY rate_date DESC, base_currency, quote_currency, user_id NULLS LAST
+        LIMIT 1000"#,
+        auth_user.user_id,
+        params.base_currency,
+        params.quote_currency,
+        params.from,
+        params.to
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(rates)))
+}
+
+// 認証済みユーザー専用のレートとして登録する。同じ日付では共通のレートより優先される
+pub async fn upload_exchange_rates(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedJson(upload): ValidatedJson<ExchangeRateUpload>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    let saved_count = save_rates(&db_pool, Some(auth_user.user_id), &upload.rates).await?;
+
+    Ok((StatusCode::OK, Json(ExchangeRateUploadResult { saved_count })))
+}
@@ -0,0 +1,560 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Json, Extension, Path},
+    response::{IntoResponse, Response},
+    http::{header, StatusCode},
+};
+use chrono::Local;
+use futures_util::{stream, StreamExt};
+use sqlx::{query_as, query, query_scalar, PgPool};
+use std::collections::HashMap;
+use std::io;
+use tokio::sync::{mpsc, Mutex};
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::ensure_user;
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::handlers::budgets::ensure_base_currency_units;
+use crate::handlers::transactions::ensure_currency;
+use crate::handlers::transfers::ensure_same_currency;
+use crate::handlers::users::fetch_base_currency;
+use crate::models::account::Account;
+use crate::models::archive::{ArchiveImportSummary, ExportFormat, ExportQuery, LedgerArchive, ARCHIVE_FORMAT_VERSION};
+use crate::models::budget::Budget;
+use crate::models::child_category::ChildCategory;
+use crate::models::money::{InCurrency, Money};
+use crate::models::parent_category::{CategoryType, ParentCategory};
+use crate::models::transaction::{Transaction, TransactionSplit, TransactionType};
+use crate::models::transfer::Transfer;
+use crate::notifications::record_budget_alerts;
+use crate::validation::{validate_minor_units, ValidatedJson, ValidatedQuery};
+
+const CSV_HEADER: [&str; 12] = [
+    "transaction_id",
+    "transaction_date",
+    "account_name",
+    "parent_category_name",
+    "child_category_name",
+    "transaction_type",
+    "amount",
+    "currency",
+    "transaction_description",
+    "split_memo",
+    "transfer_id",
+    "import_batch_id",
+];
+
+// Excel で開いたときに数式として評価されないよう、先頭が記号の文字列には ' を付ける
+fn excel_text(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_record(fields: &[String], excel: bool) -> Result<Bytes, io::Error> {
+    let terminator = if excel { csv::Terminator::CRLF } else { csv::Terminator::Any(b'\n') };
+    let mut writer = csv::WriterBuilder::new().terminator(terminator).from_writer(Vec::new());
+    writer.write_record(fields)?;
+    writer.into_inner().map(Bytes::from).map_err(|e| e.into_error())
+}
+
+// 取引を 1 行ずつ読みながら CSV を送る。分割された取引は分割行ごとに 1 行になる
+fn stream_transactions_csv(db_pool: PgPool, user_id: i32, params: ExportQuery) -> Body {
+    let excel = params.format == ExportFormat::Excel;
+    let (sender, receiver) = mpsc::channel::<Result<Bytes, io::Error>>(32);
+
+    tokio::spawn(async move {
+        let mut header = csv_record(&CSV_HEADER.map(str::to_string), excel);
+        if excel {
+            header = header.map(|line| Bytes::from([b"\xef\xbb\xbf".as_slice(), &line].concat()));
+        }
+        if sender.send(header).await.is_err() {
+            return;
+        }
+
+        let mut lines = query!(
+            r#"SELECT t.transaction_id, t.transaction_date, a.account_name,
+                p.parent_category_name AS "parent_category_name?", c.child_category_name AS "child_category_name?",
+                t.transaction_type AS "transaction_type: TransactionType", COALESCE(s.split_amount, t.transaction_amount) AS "amount!: Money", COALESCE(t.currency, a.currency) AS "currency!",
+                t.transaction_description, s.split_memo AS "split_memo?", t.transfer_id, t.import_batch_id
+            FROM Transactions t
+            JOIN Accounts a ON a.account_id = t.account_id
+            LEFT JOIN TransactionSplits s ON s.transaction_id = t.transaction_id
+            LEFT JOIN ChildCategories c ON c.child_category_id = COALESCE(s.child_category_id, t.child_category_id)
+            LEFT JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+            WHERE a.user_id = $1
+                AND ($2::date IS NULL OR t.transaction_date >= $2)
+                AND ($3::date IS NULL OR t.transaction_date <= $3)
+            ORDER BY t.transaction_date, t.transaction_id, s.split_id"#,
+            user_id,
+            params.from,
+            params.to
+        )
+        .fetch(&db_pool);
+
+        while let Some(line) = lines.next().await {
+            let chunk = match line {
+                Ok(line) => {
+                    let text = |value: Option<String>| {
+                        let value = value.unwrap_or_default();
+                        if excel { excel_text(&value) } else { value }
+                    };
+                    let id = |value: Option<i32>| value.map(|id| id.to_string()).unwrap_or_default();
+                    csv_record(
+                        &[
+                            line.transaction_id.to_string(),
+                            line.transaction_date.to_string(),
+                            text(Some(line.account_name)),
+                            text(line.parent_category_name),
+                            text(line.child_category_name),
+                            line.transaction_type.to_string(),
+                            line.amount.in_currency(&line.currency).to_string(),
+                            line.currency,
+                            text(line.transaction_description),
+                            text(line.split_memo),
+                            id(line.transfer_id),
+                            id(line.import_batch_id),
+                        ],
+                        excel,
+                    )
+                }
+                // 途中で失敗した場合は接続を切り、不完全なファイルであることをクライアントに伝える
+                Err(e) => {
+                    tracing::error!("Failed to export transactions: {:?}", e);
+                    Err(io::Error::other(e))
+                }
+            };
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    Body::from_stream(stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    }))
+}
+
+async fn build_archive(db_pool: &PgPool, user_id: i32) -> Result<LedgerArchive, ApiError> {
+    let accounts = query_as!(
+        Account,
+        r#"SELECT account_id, user_id, account_name, initial_balance, currency, created_at, account_balance(account_id) AS "current_balance: Money" FROM Accounts WHERE user_id = $1 ORDER BY account_id"#,
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let parent_categories = query_as!(
+        ParentCategory,
+        r#"SELECT parent_category_id, account_id, parent_category_name, color, category_type AS "category_type: CategoryType" FROM ParentCategories
+        WHERE account_id IN (SELECT account_id FROM Accounts WHERE user_id = $1)
+        ORDER BY parent_category_id"#,
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let child_categories = query_as!(
+        ChildCategory,
+        "SELECT c.child_category_id, c.parent_category_id, c.child_category_name FROM ChildCategories c
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        JOIN Accounts a ON a.account_id = p.account_id
+        WHERE a.user_id = $1
+        ORDER BY c.child_category_id",
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let transactions = query_as!(
+        Transaction,
+        r#"SELECT t.transaction_id, t.account_id, t.child_category_id, t.transaction_amount, t.transaction_type AS "transaction_type: TransactionType",
+            t.transaction_date, t.transaction_description, t.currency, t.transfer_id,
+            transaction_splits_json(t.transaction_id) AS "splits!: sqlx::types::Json<Vec<TransactionSplit>>"
+        FROM Transactions t
+        JOIN Accounts a ON a.account_id = t.account_id
+        WHERE a.user_id = $1 AND t.transfer_id IS NULL
+        ORDER BY t.transaction_date, t.transaction_id"#,
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let transfers = query_as!(
+        Transfer,
+        r#"SELECT tr.transfer_id, tr.from_account_id, tr.to_account_id, tr.transfer_amount, tr.transfer_date, tr.transfer_description,
+            (SELECT transaction_id FROM Transactions WHERE transfer_id = tr.transfer_id AND transaction_type = 2) AS from_transaction_id,
+            (SELECT transaction_id FROM Transactions WHERE transfer_id = tr.transfer_id AND transaction_type = 1) AS to_transaction_id
+        FROM Transfers tr
+        JOIN Accounts a ON a.account_id = tr.from_account_id
+        WHERE a.user_id = $1
+        ORDER BY tr.transfer_date, tr.transfer_id"#,
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let budgets = query_as!(
+        Budget,
+        r#"SELECT b.budget_id, b.user_id, b.child_category_id, b.amount, b.start_date, b.end_date, b.rollover_amount, b.template_id, b.alert_thresholds, p.spent AS "spent: Money", p.remaining AS "remaining: Money", p.percent_used, p.projected_spending AS "projected_spending: Money"
+        FROM Budgets b
+        CROSS JOIN LATERAL budget_progress(b.budget_id, CURRENT_DATE) p
+        WHERE b.user_id = $1
+        ORDER BY b.budget_id"#,
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    // 金額は口座の通貨 (予算は基準通貨) の桁数で書き出す
+    let base_currency = fetch_base_currency(db_pool, user_id).await?;
+    let currencies: HashMap<i32, String> = accounts
+        .iter()
+        .filter_map(|account| Some((account.account_id?, account.currency.clone()?)))
+        .collect();
+    let account_currency = |account_id: i32| currencies.get(&account_id).map_or(base_currency.as_str(), String::as_str);
+
+    Ok(LedgerArchive {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        exported_at: Some(Local::now().naive_local()),
+        transactions: transactions
+            .into_iter()
+            .map(|transaction| {
+                let currency = account_currency(transaction.account_id);
+                transaction.in_currency(currency)
+            })
+            .collect(),
+        transfers: transfers
+            .into_iter()
+            .map(|transfer| {
+                let currency = account_currency(transfer.from_account_id);
+                transfer.in_currency(currency)
+            })
+            .collect(),
+        accounts: accounts
+            .into_iter()
+            .map(|account| {
+                let currency = account.currency.clone().unwrap_or_else(|| base_currency.clone());
+                account.in_currency(&currency)
+            })
+            .collect(),
+        budgets: budgets.in_currency(&base_currency),
+        parent_categories,
+        child_categories,
+    })
+}
+
+pub async fn export_ledger(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(user_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(user_id, auth_user.user_id)?;
+
+    let date = Local::now().format("%Y%m%d");
+
+    match params.format {
+        ExportFormat::Json => {
+            let archive = build_archive(&db_pool, user_id).await?;
+            let disposition = format!("attachment; filename=\"ledger-{}.json\"", date);
+            Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(archive)).into_response())
+        }
+        ExportFormat::Csv | ExportFormat::Excel => {
+            let disposition = format!("attachment; filename=\"transactions-{}.csv\"", date);
+            let headers = [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ];
+            Ok((headers, stream_transactions_csv(db_pool, user_id, params)).into_response())
+        }
+    }
+}
+
+// アーカイブ内の ID を作成した行の ID に置き換える
+fn remap(ids: &HashMap<i32, i32>, id: i32, field: &str) -> Result<i32, ApiError> {
+    ids.get(&id).copied().ok_or_else(|| ApiError::invalid_reference(field))
+}
+
+// 取引のカテゴリの種別。category_types はエクスポート元の子カテゴリ ID ごとの種別で、カテゴリのない取引は None
+fn archive_category_type(transaction: &Transaction, category_types: &HashMap<i32, TransactionType>) -> Result<Option<TransactionType>, ApiError> {
+    let mut types = transaction
+        .child_category_id
+        .into_iter()
+        .chain(transaction.splits.iter().map(|split| split.child_category_id))
+        .map(|id| remap_type(category_types, id));
+
+    let category_type = match types.next() {
+        Some(category_type) => category_type?,
+        None => return Ok(None),
+    };
+    for other in types {
+        if other? != category_type {
+            return Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "category_type_mismatch",
+                "the categories of a split transaction must all be income or all be expense categories",
+            ).with_field("transactions.splits"));
+        }
+    }
+
+    Ok(Some(category_type))
+}
+
+// フォーマットバージョン 1 のアーカイブでは支出カテゴリへの返金などがカテゴリと逆の種別で記録されているため、
+// カテゴリの種別の負の金額の取引に直す
+fn reverse_transaction(transaction: &mut Transaction, category_type: TransactionType) {
+    transaction.transaction_type = category_type;
+    transaction.transaction_amount = -std::mem::take(&mut transaction.transaction_amount);
+    for split in transaction.splits.iter_mut() {
+        split.split_amount = -std::mem::take(&mut split.split_amount);
+    }
+}
+
+// フォーマットバージョン 1 の取引を現在の形に直す。マイグレーション 0015 と同じく、収入と支出のカテゴリが
+// 混在する分割取引は、取引と逆の種別のカテゴリの分割行を符号を反転して同じ日付の別の取引に移す
+fn upgrade_v1_transaction(mut transaction: Transaction, category_types: &HashMap<i32, TransactionType>) -> Result<Vec<Transaction>, ApiError> {
+    let split_types = transaction
+        .splits
+        .iter()
+        .map(|split| remap_type(category_types, split.child_category_id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut upgraded = Vec::new();
+    let transaction_type = transaction.transaction_type;
+    if split_types.contains(&transaction_type) && split_types.contains(&transaction_type.opposite()) {
+        let (kept, moved): (Vec<_>, Vec<_>) = std::mem::take(&mut transaction.splits.0)
+            .into_iter()
+            .zip(split_types)
+            .partition(|(_, split_type)| *split_type == transaction_type);
+
+        let moved_splits: Vec<TransactionSplit> = moved
+            .into_iter()
+            .map(|(split, _)| TransactionSplit { split_amount: -split.split_amount, ..split })
+            .collect();
+        let moved_amount: Money = moved_splits.iter().map(|split| &split.split_amount).sum();
+
+        // 移した分割行は負の金額になるため、元の取引の金額には足し戻す
+        transaction.transaction_amount += &moved_amount;
+        transaction.splits = sqlx::types::Json(kept.into_iter().map(|(split, _)| split).collect());
+        upgraded.push(Transaction {
+            transaction_id: None,
+            account_id: transaction.account_id,
+            child_category_id: None,
+            transaction_amount: moved_amount,
+            transaction_type: transaction_type.opposite(),
+            transaction_date: transaction.transaction_date,
+            transaction_description: transaction.transaction_description.clone(),
+            currency: transaction.currency.clone(),
+            transfer_id: None,
+            splits: sqlx::types::Json(moved_splits),
+        });
+    }
+
+    if let Some(category_type) = archive_category_type(&transaction, category_types)? {
+        if category_type != transaction.transaction_type {
+            reverse_transaction(&mut transaction, category_type);
+        }
+    }
+    upgraded.insert(0, transaction);
+
+    Ok(upgraded)
+}
+
+fn remap_type(category_types: &HashMap<i32, TransactionType>, id: i32) -> Result<TransactionType, ApiError> {
+    category_types.get(&id).copied().ok_or_else(|| ApiError::invalid_reference("transactions.child_category_id"))
+}
+
+// エクスポートしたアーカイブを利用者の家計簿に追加する。すべて 1 つのトランザクションで作成する
+pub async fn import_ledger(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(user_id): Path<i32>,
+    ValidatedJson(archive): ValidatedJson<LedgerArchive>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(user_id, auth_user.user_id)?;
+
+    let mut summary = ArchiveImportSummary::default();
+    let mut transaction_ids = Vec::new();
+    let mut tx = db_pool.begin().await?;
+
+    // 金額は作成時と同じく、口座・取引・振替はそれぞれの通貨、予算は基準通貨の補助単位までとする
+    let base_currency = fetch_base_currency(&mut *tx, user_id).await?;
+
+    let mut account_ids = HashMap::new();
+    for account in &archive.accounts {
+        let currency = account.currency.as_deref().unwrap_or(&base_currency);
+        validate_minor_units(currency, [("accounts.initial_balance".to_string(), &account.initial_balance)])?;
+        let account_id = query_scalar!(
+            "INSERT INTO Accounts (user_id, account_name, initial_balance, currency) VALUES ($1, $2, $3, $4) RETURNING account_id",
+            user_id,
+            account.account_name,
+            account.initial_balance.as_decimal(),
+            currency
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(old_id) = account.account_id {
+            account_ids.insert(old_id, account_id);
+        }
+        summary.accounts += 1;
+    }
+
+    let mut parent_category_ids = HashMap::new();
+    for category in &archive.parent_categories {
+        let parent_category_id = query_scalar!(
+            "INSERT INTO ParentCategories (account_id, parent_category_name, color, category_type) VALUES ($1, $2, $3, $4) RETURNING parent_category_id",
+            remap(&account_ids, category.account_id, "parent_categories.account_id")?,
+            category.parent_category_name,
+            category.color,
+            category.category_type as i32
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(old_id) = category.parent_category_id {
+            parent_category_ids.insert(old_id, parent_category_id);
+        }
+        summary.parent_categories += 1;
+    }
+
+    let parent_category_types: HashMap<i32, TransactionType> = archive
+        .parent_categories
+        .iter()
+        .filter_map(|category| Some((category.parent_category_id?, category.category_type.into())))
+        .collect();
+    let mut category_types = HashMap::new();
+    let mut child_category_ids = HashMap::new();
+    for category in &archive.child_categories {
+        let child_category_id = query_scalar!(
+            "INSERT INTO ChildCategories (parent_category_id, child_category_name) VALUES ($1, $2) RETURNING child_category_id",
+            remap(&parent_category_ids, category.parent_category_id, "child_categories.parent_category_id")?,
+            category.child_category_name
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(old_id) = category.child_category_id {
+            child_category_ids.insert(old_id, child_category_id);
+            if let Some(category_type) = parent_category_types.get(&category.parent_category_id) {
+                category_types.insert(old_id, *category_type);
+            }
+        }
+        summary.child_categories += 1;
+    }
+
+    let transactions = if archive.format_version == 1 {
+        let mut upgraded = Vec::new();
+        for transaction in archive.transactions {
+            upgraded.extend(upgrade_v1_transaction(transaction, &category_types)?);
+        }
+        upgraded
+    } else {
+        archive.transactions
+    };
+    for transaction in transactions {
+        if archive_category_type(&transaction, &category_types)?.is_some_and(|category_type| category_type != transaction.transaction_type) {
+            return Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "category_type_mismatch",
+                "transaction_type must match the category type; record money moving the other way as a negative amount",
+            ).with_field("transactions.transaction_type"));
+        }
+        let child_category_id = match transaction.child_category_id {
+            Some(id) => Some(remap(&child_category_ids, id, "transactions.child_category_id")?),
+            None => None,
+        };
+        let account_id = remap(&account_ids, transaction.account_id, "transactions.account_id")?;
+        ensure_currency(&mut *tx, account_id, &transaction).await.map_err(|e| e.in_field("transactions"))?;
+        let transaction_id = query_scalar!(
+            "INSERT INTO Transactions (account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description, currency) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING transaction_id",
+            account_id,
+            child_category_id,
+            transaction.transaction_amount.as_decimal(),
+            transaction.transaction_type as i32,
+            transaction.transaction_date,
+            transaction.transaction_description,
+            transaction.currency
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        transaction_ids.push(transaction_id);
+
+        for split in transaction.splits.iter() {
+            query!(
+                "INSERT INTO TransactionSplits (transaction_id, child_category_id, split_amount, split_memo) VALUES ($1, $2, $3, $4)",
+                transaction_id,
+                remap(&child_category_ids, split.child_category_id, "transactions.splits.child_category_id")?,
+                split.split_amount.as_decimal(),
+                split.split_memo
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        summary.transactions += 1;
+    }
+
+    for transfer in &archive.transfers {
+        let from_account_id = remap(&account_ids, transfer.from_account_id, "transfers.from_account_id")?;
+        let to_account_id = remap(&account_ids, transfer.to_account_id, "transfers.to_account_id")?;
+        let currency = ensure_same_currency(&mut *tx, from_account_id, to_account_id)
+            .await
+            .map_err(|e| e.in_field("transfers"))?;
+        validate_minor_units(&currency, [("transfers.transfer_amount".to_string(), &transfer.transfer_amount)])?;
+
+        let transfer_id = query_scalar!(
+            "INSERT INTO Transfers (from_account_id, to_account_id, transfer_amount, transfer_date, transfer_description) VALUES ($1, $2, $3, $4, $5) RETURNING transfer_id",
+            from_account_id,
+            to_account_id,
+            transfer.transfer_amount.as_decimal(),
+            transfer.transfer_date,
+            transfer.transfer_description
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let transfer_transaction_ids = query_scalar!(
+            "INSERT INTO Transactions (account_id, transaction_amount, transaction_type, transaction_date, transaction_description, transfer_id) VALUES ($1, $3, 2, $4, $5, $6), ($2, $3, 1, $4, $5, $6) RETURNING transaction_id",
+            from_account_id,
+            to_account_id,
+            transfer.transfer_amount.as_decimal(),
+            transfer.transfer_date,
+            transfer.transfer_description,
+            transfer_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        transaction_ids.extend(transfer_transaction_ids);
+        summary.transfers += 1;
+    }
+
+    for budget in &archive.budgets {
+        ensure_base_currency_units(&base_currency, budget).map_err(|e| e.in_field("budgets"))?;
+        query!(
+            "INSERT INTO Budgets (user_id, child_category_id, amount, start_date, end_date, rollover_amount, alert_thresholds) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            user_id,
+            remap(&child_category_ids, budget.child_category_id, "budgets.child_category_id")?,
+            budget.amount.as_decimal(),
+            budget.start_date,
+            budget.end_date,
+            budget.rollover_amount.as_decimal(),
+            &budget.alert_thresholds
+        )
+        .execute(&mut *tx)
+        .await?;
+        summary.budgets += 1;
+    }
+
+    // 予算を作成した後で、取り込んだ取引によってしきい値に達した予算を通知する
+    record_budget_alerts(&mut *tx, &transaction_ids).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(summary)))
+}
@@ -0,0 +1,151 @@
+use axum::{
+    extract::{Json, Extension, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use chrono::{Days, Local, Months};
+use sqlx::types::BigDecimal;
+use sqlx::{query_as, query_scalar};
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::ensure_account_owner;
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::models::forecast::{CashFlowForecast, ForecastPoint, ForecastQuery, LowBalanceWarning, VariableFlow};
+use crate::handlers::accounts::fetch_account_currency;
+use crate::models::money::{InCurrency, Money};
+use crate::models::recurring::{Frequency, RecurringSchedule};
+use crate::models::transaction::TransactionType;
+use crate::validation::ValidatedQuery;
+
+// 現在残高から、定期取引の予定と直近の変動費の平均で明日以降の残高を 1 日ずつ予測する。
+// 計上が遅れている定期取引は予測の初日に計上されるものとして扱う
+pub async fn get_cash_flow_forecast(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<ForecastQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let today = Local::now().date_naive();
+    let invalid_range = || ApiError::internal("cash flow forecast", "date out of range");
+    let start_date = today.succ_opt().ok_or_else(invalid_range)?;
+    let end_date = today.checked_add_months(Months::new(params.months)).ok_or_else(invalid_range)?;
+    let history_start = today.checked_sub_months(Months::new(params.history_months)).ok_or_else(invalid_range)?;
+    let history_days = (today - history_start).num_days();
+
+    // 口座の通貨に換算できない取引があると残高は NULL になる
+    let starting_balance = query_scalar!(
+        r#"SELECT account_balance($1) AS "balance: Money""#,
+        account_id
+    )
+    .fetch_one(&db_pool)
+    .await?
+    .ok_or_else(|| ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "missing_exchange_rate",
+        "the account has transactions without an exchange rate to the account currency",
+    ))?;
+
+    // 定期取引から計上された取引は予定として別に数えるため平均から除く
+    let variable_flows = query_as!(
+        VariableFlow,
+        r#"SELECT c.child_category_id, c.child_category_name,
+            ROUND(SUM(CASE WHEN l.transaction_type = 1 THEN 1 ELSE -1 END
+                * account_amount(l.amount, l.currency, l.transaction_date, l.account_id)) / $4, 4) AS "daily_average!"
+        FROM TransactionCategoryLines l
+        JOIN Transactions t ON t.transaction_id = l.transaction_id
+        JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+        WHERE l.account_id = $1
+            AND t.schedule_id IS NULL
+            AND l.transaction_date > $2
+            AND l.transaction_date <= $3
+        GROUP BY c.child_category_id, c.child_category_name
+        ORDER BY c.child_category_id"#,
+        account_id,
+        history_start,
+        today,
+        BigDecimal::from(history_days)
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let schedules = query_as!(
+        RecurringSchedule,
+        r#"SELECT schedule_id, account_id, child_category_id, transaction_amount, transaction_type AS "transaction_type: TransactionType",
+            transaction_description, frequency AS "frequency: Frequency", interval_count, day_of_month, start_date, end_date, occurrence_count, posted_through
+        FROM RecurringSchedules
+        WHERE account_id = $1"#,
+        account_id
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let mut recurring: BTreeMap<_, Money> = BTreeMap::new();
+    for schedule in &schedules {
+        let amount = if schedule.transaction_type == TransactionType::Income {
+            schedule.transaction_amount.clone()
+        } else {
+            -schedule.transaction_amount.clone()
+        };
+        for occurrence_date in schedule.pending_occurrences().take_while(|date| *date <= end_date) {
+            *recurring.entry(occurrence_date.max(start_date)).or_default() += &amount;
+        }
+    }
+
+    let variable = Money::from(variable_flows.iter().map(|flow| &flow.daily_average).sum::<BigDecimal>());
+    let threshold = params.low_balance_threshold.unwrap_or_default();
+
+    let mut balance = starting_balance.clone();
+    let mut series = Vec::new();
+    let mut warnings: Vec<LowBalanceWarning> = Vec::new();
+    let mut below = false;
+    let mut date = start_date;
+    while date <= end_date {
+        let recurring_amount = recurring.remove(&date).unwrap_or_default();
+        balance += &recurring_amount + &variable;
+        let rounded = balance.round();
+
+        if rounded < threshold {
+            match warnings.last_mut() {
+                Some(warning) if below => {
+                    warning.end_date = date;
+                    if rounded < warning.lowest_balance {
+                        warning.lowest_date = date;
+                        warning.lowest_balance = rounded.clone();
+                    }
+                }
+                _ => warnings.push(LowBalanceWarning {
+                    start_date: date,
+                    end_date: date,
+                    lowest_date: date,
+                    lowest_balance: rounded.clone(),
+                }),
+            }
+            below = true;
+        } else {
+            below = false;
+        }
+
+        series.push(ForecastPoint { date, recurring: recurring_amount, variable: variable.round(), balance: rounded });
+        date = date.checked_add_days(Days::new(1)).ok_or_else(invalid_range)?;
+    }
+
+    let currency = fetch_account_currency(&db_pool, account_id).await?;
+
+    Ok((StatusCode::OK, Json(CashFlowForecast {
+        account_id,
+        start_date,
+        end_date,
+        starting_balance,
+        low_balance_threshold: threshold,
+        variable_flows,
+        series,
+        warnings,
+    }.in_currency(&currency))))
+}
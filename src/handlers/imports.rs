@@ -0,0 +1,228 @@
+use axum::{
+    body::Bytes,
+    extract::{Json, Extension, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sqlx::{query_as, query, query_scalar, PgExecutor};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_account_owner, ensure_child_category_owner, ensure_import_batch_owner, ensure_import_mapping_owner};
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::handlers::accounts::fetch_account_currency;
+use crate::import;
+use crate::models::import::{AmountSign, ImportBatch, ImportFormat, ImportMapping, ImportQuery};
+use crate::models::money::InCurrency;
+use crate::validation::{ValidatedJson, ValidatedQuery};
+
+async fn fetch_mapping<'e>(executor: impl PgExecutor<'e>, mapping_id: i32) -> Result<ImportMapping, ApiError> {
+    let mapping = query_as!(
+        ImportMapping,
+        r#"SELECT mapping_id, account_id, mapping_name, date_column, date_format, amount_column, amount_sign AS "amount_sign: AmountSign", description_column
+        FROM ImportMappings
+        WHERE mapping_id = $1"#,
+        mapping_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(mapping)
+}
+
+// アップロードされたファイルを UTF-8 の文字列として読む。先頭の BOM は取り除く
+pub(crate) fn decode_upload(body: &Bytes) -> Result<&str, ApiError> {
+    let text = std::str::from_utf8(body)
+        .map_err(|_| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_body", "the uploaded file must be UTF-8 encoded"))?;
+    Ok(text.strip_prefix('\u{feff}').unwrap_or(text))
+}
+
+pub async fn create_import_mapping(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedJson(mapping): ValidatedJson<ImportMapping>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, mapping.account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("account_id"))?;
+
+    let mapping_id = query_scalar!(
+        "INSERT INTO ImportMappings (account_id, mapping_name, date_column, date_format, amount_column, amount_sign, description_column) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING mapping_id",
+        mapping.account_id,
+        mapping.mapping_name,
+        mapping.date_column,
+        mapping.date_format,
+        mapping.amount_column,
+        mapping.amount_sign as AmountSign,
+        mapping.description_column
+    )
+    .fetch_one(&db_pool)
+    .await?;
+
+    let new_mapping = fetch_mapping(&db_pool, mapping_id).await?;
+
+    Ok((StatusCode::CREATED, Json(new_mapping)))
+}
+
+pub async fn list_import_mappings(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let mappings = query_as!(
+        ImportMapping,
+        r#"SELECT mapping_id, account_id, mapping_name, date_column, date_format, amount_column, amount_sign AS "amount_sign: AmountSign", description_column
+        FROM ImportMappings
+        WHERE account_id = $1
+        ORDER BY mapping_name"#,
+        account_id
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(mappings)))
+}
+
+pub async fn update_import_mapping(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(mapping_id): Path<i32>,
+    ValidatedJson(mapping): ValidatedJson<ImportMapping>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_import_mapping_owner(&db_pool, mapping_id, auth_user.user_id).await?;
+
+    query!(
+        "UPDATE ImportMappings SET mapping_name = $1, date_column = $2, date_format = $3, amount_column = $4, amount_sign = $5, description_column = $6 WHERE mapping_id = $7",
+        mapping.mapping_name,
+        mapping.date_column,
+        mapping.date_format,
+        mapping.amount_column,
+        mapping.amount_sign as AmountSign,
+        mapping.description_column,
+        mapping_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    let updated_mapping = fetch_mapping(&db_pool, mapping_id).await?;
+
+    Ok((StatusCode::OK, Json(updated_mapping)))
+}
+
+pub async fn delete_import_mapping(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(mapping_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_import_mapping_owner(&db_pool, mapping_id, auth_user.user_id).await?;
+
+    query!(
+        "DELETE FROM ImportMappings WHERE mapping_id = $1",
+        mapping_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// リクエストボディの明細を取り込む。dry_run=true の場合は判定結果だけを返す
+pub async fn import_statement(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<ImportQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+    ensure_child_category_owner(&db_pool, params.child_category_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("child_category_id"))?;
+
+    let currency = fetch_account_currency(&db_pool, account_id).await?;
+    let data = decode_upload(&body)?;
+
+    let rows = match (params.format, params.mapping_id) {
+        (ImportFormat::Csv, Some(mapping_id)) => {
+            let mapping = fetch_mapping(&db_pool, mapping_id)
+                .await
+                .map_err(|e| e.into_invalid_reference("mapping_id"))?;
+            if mapping.account_id != account_id {
+                return Err(ApiError::invalid_reference("mapping_id"));
+            }
+            import::csv::parse(&mapping, data)?
+        }
+        (ImportFormat::Ofx, _) => import::ofx::parse(data)?,
+        (ImportFormat::Camt053, _) => import::camt::parse(data, &currency)?,
+        // mapping_id の有無は ImportQuery の検証で確認済み
+        (ImportFormat::Csv, None) => return Err(ApiError::invalid_reference("mapping_id")),
+    };
+
+    let result = import::run_import(
+        &db_pool,
+        account_id,
+        params.mapping_id,
+        params.format.as_str(),
+        params.child_category_id,
+        params.dry_run,
+        rows,
+    )
+    .await?;
+
+    let status = if result.import_batch_id.is_some() { StatusCode::CREATED } else { StatusCode::OK };
+
+    Ok((status, Json(result.in_currency(&currency))))
+}
+
+pub async fn list_import_batches(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let batches = query_as!(
+        ImportBatch,
+        "SELECT import_batch_id, account_id, mapping_id, source, row_count, created_at FROM ImportBatches WHERE account_id = $1 ORDER BY import_batch_id DESC",
+        account_id
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(batches)))
+}
+
+pub async fn undo_import_batch(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(import_batch_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_import_batch_owner(&db_pool, import_batch_id, auth_user.user_id).await?;
+
+    // 取り込んだ取引は ON DELETE CASCADE でまとめて削除される
+    query!(
+        "DELETE FROM ImportBatches WHERE import_batch_id = $1",
+        import_batch_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,5 +1,15 @@
+pub mod auth;
 pub mod users;
 pub mod accounts;
 pub mod categories;
 pub mod transactions;
-pub mod budgets;
\ No newline at end of file
+pub mod transfers;
+pub mod budgets;
+pub mod budget_templates;
+pub mod recurring;
+pub mod imports;
+pub mod export;
+pub mod notifications;
+pub mod reports;
+pub mod forecast;
+pub mod exchange_rates;
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Json, Extension, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sqlx::{query_as, query, PgExecutor};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::ensure_notification_owner;
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::models::notification::{Notification, NotificationListQuery, NotificationUpdate};
+use crate::validation::{ValidatedJson, ValidatedQuery};
+
+async fn fetch_notification<'e>(executor: impl PgExecutor<'e>, notification_id: i32) -> Result<Notification, ApiError> {
+    let notification = query_as!(
+        Notification,
+        "SELECT notification_id, user_id, budget_id, threshold, notification_title, notification_message, created_at, read_at, delivered_at
+        FROM Notifications
+        WHERE notification_id = $1",
+        notification_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(notification)
+}
+
+// 認証済みユーザーの通知を新しい順に返す
+pub async fn list_notifications(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedQuery(params): ValidatedQuery<NotificationListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    let notifications = query_as!(
+        Notification,
+        "SELECT notification_id, user_id, budget_id, threshold, notification_title, notification_message, created_at, read_at, delivered_at
+        FROM Notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+        ORDER BY notification_id DESC
+        LIMIT $3",
+        auth_user.user_id,
+        params.unread,
+        params.limit
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(notifications)))
+}
+
+// 既読・未読を切り替える
+pub async fn update_notification(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(notification_id): Path<i32>,
+    ValidatedJson(update): ValidatedJson<NotificationUpdate>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_notification_owner(&db_pool, notification_id, auth_user.user_id).await?;
+
+    // 既読の通知を再度既読にしても既読にした日時は変えない
+    query!(
+        "UPDATE Notifications SET read_at = CASE WHEN $1 THEN COALESCE(read_at, CURRENT_TIMESTAMP) END WHERE notification_id = $2",
+        update.read,
+        notification_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    let updated_notification = fetch_notification(&db_pool, notification_id).await?;
+
+    Ok((StatusCode::OK, Json(updated_notification)))
+}
+
+pub async fn mark_all_notifications_read(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    query!(
+        "UPDATE Notifications SET read_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND read_at IS NULL",
+        auth_user.user_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
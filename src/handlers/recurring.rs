@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Json, Extension, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sqlx::{query_as, query, query_scalar, PgExecutor, PgPool};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_account_owner, ensure_child_category_owner, ensure_schedule_owner};
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::handlers::accounts::fetch_account_currency;
+use crate::handlers::transactions::ensure_category_type;
+use crate::models::money::InCurrency;
+use crate::models::recurring::{Frequency, RecurringSchedule, ScheduledOccurrence, SchedulePreviewQuery};
+use crate::models::transaction::TransactionType;
+use crate::validation::{validate_minor_units, ValidatedJson, ValidatedQuery};
+
+async fn ensure_references_owner(db_pool: &PgPool, schedule: &RecurringSchedule, user_id: i32) -> Result<(), ApiError> {
+    ensure_account_owner(db_pool, schedule.account_id, user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("account_id"))?;
+    ensure_child_category_owner(db_pool, schedule.child_category_id, user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("child_category_id"))?;
+    ensure_category_type(db_pool, schedule.child_category_id, schedule.transaction_type, "child_category_id").await?;
+
+    Ok(())
+}
+
+async fn fetch_schedule<'e>(executor: impl PgExecutor<'e>, schedule_id: i32) -> Result<RecurringSchedule, ApiError> {
+    let schedule = query_as!(
+        RecurringSchedule,
+        r#"SELECT schedule_id, account_id, child_category_id, transaction_amount, transaction_type AS "transaction_type: TransactionType",
+            transaction_description, frequency AS "frequency: Frequency", interval_count, day_of_month, start_date, end_date, occurrence_count, posted_through
+        FROM RecurringSchedules
+        WHERE schedule_id = $1"#,
+        schedule_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(schedule)
+}
+
+pub async fn create_schedule(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedJson(schedule): ValidatedJson<RecurringSchedule>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_references_owner(&db_pool, &schedule, auth_user.user_id).await?;
+
+    let currency = fetch_account_currency(&db_pool, schedule.account_id).await?;
+    validate_minor_units(&currency, [("transaction_amount".to_string(), &schedule.transaction_amount)])?;
+
+    let schedule_id = query_scalar!(
+        "INSERT INTO RecurringSchedules (account_id, child_category_id, transaction_amount, transaction_type, transaction_description, frequency, interval_count, day_of_month, start_date, end_date, occurrence_count, next_occurrence)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING schedule_id",
+        schedule.account_id,
+        schedule.child_category_id,
+        schedule.transaction_amount.as_decimal(),
+        schedule.transaction_type as i32,
+        schedule.transaction_description,
+        schedule.frequency as Frequency,
+        schedule.interval_count,
+        schedule.day_of_month,
+        schedule.start_date,
+        schedule.end_date,
+        schedule.occurrence_count,
+        schedule.occurrences().next()
+    )
+    .fetch_one(&db_pool)
+    .await?;
+
+    let new_schedule = fetch_schedule(&db_pool, schedule_id).await?;
+
+    Ok((StatusCode::CREATED, Json(new_schedule.in_currency(&currency))))
+}
+
+pub async fn get_schedule(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(schedule_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_schedule_owner(&db_pool, schedule_id, auth_user.user_id).await?;
+
+    let schedule = fetch_schedule(&db_pool, schedule_id).await?;
+    let currency = fetch_account_currency(&db_pool, schedule.account_id).await?;
+
+    Ok((StatusCode::OK, Json(schedule.in_currency(&currency))))
+}
+
+pub async fn list_account_schedules(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let schedules = query_as!(
+        RecurringSchedule,
+        r#"SELECT schedule_id, account_id, child_category_id, transaction_amount, transaction_type AS "transaction_type: TransactionType",
+            transaction_description, frequency AS "frequency: Frequency", interval_count, day_of_month, start_date, end_date, occurrence_count, posted_through
+        FROM RecurringSchedules
+        WHERE account_id = $1
+        ORDER BY schedule_id"#,
+        account_id
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let currency = fetch_account_currency(&db_pool, account_id).await?;
+
+    Ok((StatusCode::OK, Json(schedules.in_currency(&currency))))
+}
+
+// 計上済みの日付は保持したまま、以降の発生分に新しい内容を適用する
+// 次の発生日は最初の発生日に戻し、計上済みの発生日は次の定期処理で読み飛ばす
+pub async fn update_schedule(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(schedule_id): Path<i32>,
+    ValidatedJson(schedule): ValidatedJson<RecurringSchedule>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_schedule_owner(&db_pool, schedule_id, auth_user.user_id).await?;
+    ensure_references_owner(&db_pool, &schedule, auth_user.user_id).await?;
+
+    let currency = fetch_account_currency(&db_pool, schedule.account_id).await?;
+    validate_minor_units(&currency, [("transaction_amount".to_string(), &schedule.transaction_amount)])?;
+
+    query!(
+        "UPDATE RecurringSchedules SET account_id = $1, child_category_id = $2, transaction_amount = $3, transaction_type = $4, transaction_description = $5,
+            frequency = $6, interval_count = $7, day_of_month = $8, start_date = $9, end_date = $10, occurrence_count = $11, next_occurrence = $12
+        WHERE schedule_id = $13",
+        schedule.account_id,
+        schedule.child_category_id,
+        schedule.transaction_amount.as_decimal(),
+        schedule.transaction_type as i32,
+        schedule.transaction_description,
+        schedule.frequency as Frequency,
+        schedule.interval_count,
+        schedule.day_of_month,
+        schedule.start_date,
+        schedule.end_date,
+        schedule.occurrence_count,
+        schedule.occurrences().next(),
+        schedule_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    let updated_schedule = fetch_schedule(&db_pool, schedule_id).await?;
+
+    Ok((StatusCode::OK, Json(updated_schedule.in_currency(&currency))))
+}
+
+pub async fn delete_schedule(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(schedule_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_schedule_owner(&db_pool, schedule_id, auth_user.user_id).await?;
+
+    // 計上済みの取引は ON DELETE SET NULL で通常の取引として残る
+    query!(
+        "DELETE FROM RecurringSchedules WHERE schedule_id = $1",
+        schedule_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// まだ計上されていない今後の発生分を返す
+pub async fn preview_schedule(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(schedule_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<SchedulePreviewQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_schedule_owner(&db_pool, schedule_id, auth_user.user_id).await?;
+
+    let schedule = fetch_schedule(&db_pool, schedule_id).await?;
+    let currency = fetch_account_currency(&db_pool, schedule.account_id).await?;
+
+    let occurrences: Vec<ScheduledOccurrence> = schedule
+        .pending_occurrences()
+        .take_while(|date| params.until.is_none_or(|until| *date <= until))
+        .take(params.limit as usize)
+        .map(|occurrence_date| ScheduledOccurrence {
+            schedule_id,
+            occurrence_date,
+            account_id: schedule.account_id,
+            child_category_id: schedule.child_category_id,
+            transaction_amount: schedule.transaction_amount.clone(),
+            transaction_type: schedule.transaction_type,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(occurrences.in_currency(&currency))))
+}
@@ -0,0 +1,273 @@
+use axum::{
+    extract::{Json, Extension},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sqlx::types::BigDecimal;
+use sqlx::{query, query_as};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_account_owner, ensure_user};
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::handlers::exchange_rates::ensure_base_currency_rates;
+use crate::models::money::{InCurrency, Money};
+use crate::models::period::Month;
+use crate::models::report::{
+    CategoryComparison, CategoryReport, CategoryReportQuery, ChildCategoryTotal, ComparisonBase, ComparisonReport, ComparisonReportQuery,
+    ParentCategoryTotal, SummaryPeriod, SummaryReport, SummaryReportQuery, SummaryTotals,
+};
+use crate::validation::ValidatedQuery;
+
+// 期間ごとの収入・支出・貯蓄額。親カテゴリの種類で収入か支出かを決め、
+// 種類と逆向きの取引 (支出カテゴリへの返金など) は負の金額なのでその合計から差し引かれる
+pub async fn get_summary_report(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedQuery(params): ValidatedQuery<SummaryReportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(params.user_id, auth_user.user_id)?;
+    let currency = ensure_base_currency_rates(&db_pool, params.user_id, params.from, params.to, None).await?;
+
+    // ROLLUP で期間ごとの行と全期間の合計行 (is_total) をまとめて求める。
+    // 取引のない期間も返すため generate_series で期間を補完する
+    let rows = query!(
+        r#"WITH periods AS (
+            SELECT generate_series(
+                date_trunc($2, $3::date::timestamp),
+                date_trunc($2, $4::date::timestamp),
+                ('1 ' || $2)::interval
+            )::date AS period_start
+        ),
+        flows AS (
+            SELECT date_trunc($2, l.transaction_date::timestamp)::date AS period_start,
+                SUM(CASE WHEN p.category_type = 1 THEN base_amount(l.amount, l.currency, l.transaction_date, a.user_id) ELSE 0 END) AS income,
+                SUM(CASE WHEN p.category_type = 2 THEN base_amount(l.amount, l.currency, l.transaction_date, a.user_id) ELSE 0 END) AS expense
+            FROM TransactionCategoryLines l
+            JOIN Accounts a ON a.account_id = l.account_id
+            JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+            JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+            WHERE a.user_id = $1 AND l.transaction_date BETWEEN $3 AND $4
+            GROUP BY 1
+        ),
+        totals AS (
+            SELECT pr.period_start,
+                GROUPING(pr.period_start) = 1 AS is_total,
+                COALESCE(SUM(f.income), 0) AS income,
+                COALESCE(SUM(f.expense), 0) AS expense
+            FROM periods pr
+            LEFT JOIN flows f ON f.period_start = pr.period_start
+            GROUP BY ROLLUP (pr.period_start)
+        )
+        SELECT is_total AS "is_total!",
+            GREATEST(period_start, $3) AS "period_start!",
+            LEAST((period_start + ('1 ' || $2)::interval - INTERVAL '1 day')::date, $4) AS "period_end!",
+            income AS "income!: Money",
+            expense AS "expense!: Money",
+            income - expense AS "net_savings!: Money",
+            ROUND((income - expense) * 100 / NULLIF(income, 0), 1) AS savings_rate
+        FROM totals
+        ORDER BY is_total, period_start"#,
+        params.user_id,
+        params.granularity.as_str(),
+        params.from,
+        params.to
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let mut periods = Vec::new();
+    let mut total = None;
+    for row in rows {
+        let totals = SummaryTotals {
+            income: row.income,
+            expense: row.expense,
+            net_savings: row.net_savings,
+            savings_rate: row.savings_rate,
+        };
+        if row.is_total {
+            total = Some(totals);
+        } else {
+            periods.push(SummaryPeriod { period_start: row.period_start, period_end: row.period_end, totals });
+        }
+    }
+
+    let total = total.ok_or_else(|| ApiError::internal("summary report", "the query returned no total row"))?;
+
+    Ok((StatusCode::OK, Json(SummaryReport {
+        from: params.from,
+        to: params.to,
+        periods: periods.in_currency(&currency),
+        total: total.in_currency(&currency),
+        currency,
+    })))
+}
+
+// 支出カテゴリごとの支出額。親カテゴリの合計と割合はウィンドウ関数で求め、
+// 支出カテゴリへの返金 (負の金額の支出) は支出から差し引かれる
+pub async fn get_category_report(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedQuery(params): ValidatedQuery<CategoryReportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(params.user_id, auth_user.user_id)?;
+    if let Some(account_id) = params.account_id {
+        ensure_account_owner(&db_pool, account_id, auth_user.user_id)
+            .await
+            .map_err(|e| e.into_invalid_reference("account_id"))?;
+    }
+    let currency = ensure_base_currency_rates(&db_pool, params.user_id, params.from, params.to, params.account_id).await?;
+
+    let rows = query!(
+        r#"WITH children AS (
+            SELECT p.parent_category_id, p.parent_category_name, p.color, c.child_category_id, c.child_category_name,
+                SUM(base_amount(l.amount, l.currency, l.transaction_date, a.user_id)) AS total
+            FROM TransactionCategoryLines l
+            JOIN Accounts a ON a.account_id = l.account_id
+            JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+            JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+            WHERE a.user_id = $1
+                AND p.category_type = 2
+                AND l.transaction_date BETWEEN $2 AND $3
+                AND ($4::int IS NULL OR l.account_id = $4)
+            GROUP BY p.parent_category_id, p.parent_category_name, p.color, c.child_category_id, c.child_category_name
+        ),
+        shares AS (
+            SELECT *,
+                SUM(total) OVER (PARTITION BY parent_category_id) AS parent_total,
+                SUM(total) OVER () AS grand_total
+            FROM children
+        )
+        SELECT parent_category_id, parent_category_name, color, child_category_id, child_category_name,
+            total AS "total!: Money",
+            parent_total AS "parent_total!: Money",
+            grand_total AS "grand_total!: Money",
+            ROUND(total * 100 / NULLIF(grand_total, 0), 1) AS child_share,
+            ROUND(parent_total * 100 / NULLIF(grand_total, 0), 1) AS parent_share
+        FROM shares
+        ORDER BY parent_total DESC, parent_category_id, total DESC, child_category_id"#,
+        params.user_id,
+        params.from,
+        params.to,
+        params.account_id
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let total = rows.first().map(|row| row.grand_total.clone()).unwrap_or_default();
+
+    let mut categories: Vec<ParentCategoryTotal> = Vec::new();
+    for row in rows {
+        if categories.last().map(|parent| parent.parent_category_id) != Some(row.parent_category_id) {
+            categories.push(ParentCategoryTotal {
+                parent_category_id: row.parent_category_id,
+                parent_category_name: row.parent_category_name,
+                color: row.color,
+                total: row.parent_total,
+                share: row.parent_share,
+                children: Vec::new(),
+            });
+        }
+        if let Some(parent) = categories.last_mut() {
+            parent.children.push(ChildCategoryTotal {
+                child_category_id: row.child_category_id,
+                child_category_name: row.child_category_name,
+                total: row.total,
+                share: row.child_share,
+            });
+        }
+    }
+
+    Ok((StatusCode::OK, Json(CategoryReport {
+        from: params.from,
+        to: params.to,
+        total: total.in_currency(&currency),
+        categories: categories.in_currency(&currency),
+        currency,
+    })))
+}
+
+// 子カテゴリごとに、指定月の支出を比較対象の月および直前 average_months か月の平均と比べる
+pub async fn get_comparison_report(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedQuery(params): ValidatedQuery<ComparisonReportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_user(params.user_id, auth_user.user_id)?;
+
+    let period = params.period.unwrap_or_else(Month::current);
+    let baseline = match params.compare_to {
+        ComparisonBase::PreviousMonth => period.months_before(1),
+        ComparisonBase::PreviousYear => period.months_before(12),
+    };
+    let average_start = period.months_before(params.average_months);
+    let range_start = baseline.first_day().min(average_start.first_day());
+    let currency = ensure_base_currency_rates(&db_pool, params.user_id, range_start, period.last_day(), None).await?;
+
+    let categories = query_as!(
+        CategoryComparison,
+        r#"WITH monthly AS (
+            SELECT l.child_category_id,
+                date_trunc('month', l.transaction_date::timestamp)::date AS month,
+                SUM(base_amount(l.amount, l.currency, l.transaction_date, a.user_id)) AS total
+            FROM TransactionCategoryLines l
+            JOIN Accounts a ON a.account_id = l.account_id
+            JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+            JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+            WHERE a.user_id = $1
+                AND p.category_type = 2
+                AND l.transaction_date BETWEEN $2 AND $3
+            GROUP BY 1, 2
+        ),
+        totals AS (
+            SELECT child_category_id,
+                COALESCE(SUM(total) FILTER (WHERE month = $4), 0) AS current,
+                COALESCE(SUM(total) FILTER (WHERE month = $5), 0) AS baseline,
+                COALESCE(SUM(total) FILTER (WHERE month >= $6 AND month < $4), 0) / $7 AS average
+            FROM monthly
+            GROUP BY child_category_id
+        )
+        SELECT c.child_category_id, c.child_category_name, p.parent_category_id, p.parent_category_name,
+            t.current AS "current!",
+            t.baseline AS "baseline!",
+            t.current - t.baseline AS "change!",
+            ROUND((t.current - t.baseline) * 100 / NULLIF(t.baseline, 0), 1) AS change_percent,
+            ROUND(t.average, 2) AS "average!",
+            ROUND((t.current - t.average) * 100 / NULLIF(t.average, 0), 1) AS deviation_percent,
+            CASE WHEN t.average = 0 THEN t.current <> 0
+                ELSE ABS(t.current - t.average) * 100 / ABS(t.average) >= $8
+            END AS "significant!"
+        FROM totals t
+        JOIN ChildCategories c ON c.child_category_id = t.child_category_id
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        ORDER BY ABS(t.current - t.baseline) DESC, c.child_category_id"#,
+        params.user_id,
+        range_start,
+        period.last_day(),
+        period.first_day(),
+        baseline.first_day(),
+        average_start.first_day(),
+        BigDecimal::from(params.average_months),
+        BigDecimal::from(params.deviation_threshold)
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(ComparisonReport {
+        period_start: period.first_day(),
+        period_end: period.last_day(),
+        baseline_start: baseline.first_day(),
+        baseline_end: baseline.last_day(),
+        average_start: average_start.first_day(),
+        average_months: params.average_months,
+        categories: categories.in_currency(&currency),
+        currency,
+    })))
+}
@@ -3,93 +3,402 @@ use axum::{
     response::IntoResponse,
     http::StatusCode,
 };
-use sqlx::{query_as, query};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDate;
+use sqlx::{query_as, query, query_scalar, PgExecutor, PgPool};
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_account_owner, ensure_child_category_owner, ensure_transaction_owner};
 use crate::db::AppState;
-use crate::models::transaction::Transaction;
+use crate::error::ApiError;
+use crate::handlers::accounts::fetch_account_currency;
+use crate::models::money::{InCurrency, Money};
+use crate::models::parent_category::CategoryType;
+use crate::models::transaction::{Transaction, TransactionListQuery, TransactionPage, TransactionSplit, TransactionType};
+use crate::notifications::record_budget_alerts;
+use crate::validation::{validate_minor_units, ValidatedJson, ValidatedQuery};
+
+// 振替の取引は /transfers から 2 つまとめて変更する
+async fn ensure_not_transfer(db_pool: &PgPool, transaction_id: i32) -> Result<(), ApiError> {
+    let transfer_id = query_scalar!(
+        "SELECT transfer_id FROM Transactions WHERE transaction_id = $1",
+        transaction_id
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    match transfer_id {
+        Some(_) => Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "managed_by_transfer",
+            "the transaction belongs to a transfer and must be changed through /transfers",
+        )),
+        None => Ok(()),
+    }
+}
+
+// 取引本体のカテゴリと分割行のカテゴリがすべて利用者のものか確認する
+async fn ensure_categories_owner(db_pool: &PgPool, transaction: &Transaction, user_id: i32) -> Result<(), ApiError> {
+    if let Some(child_category_id) = transaction.child_category_id {
+        ensure_child_category_owner(db_pool, child_category_id, user_id)
+            .await
+            .map_err(|e| e.into_invalid_reference("child_category_id"))?;
+    }
+
+    for split in transaction.splits.iter() {
+        ensure_child_category_owner(db_pool, split.child_category_id, user_id)
+            .await
+            .map_err(|e| e.into_invalid_reference("splits"))?;
+    }
+
+    Ok(())
+}
+
+// 子カテゴリが属する親カテゴリの種別。取引の種別はこれと同じにする
+pub(crate) async fn fetch_category_type<'e>(executor: impl PgExecutor<'e>, child_category_id: i32) -> Result<TransactionType, ApiError> {
+    let category_type = query_scalar!(
+        r#"SELECT p.category_type AS "category_type: CategoryType"
+        FROM ChildCategories c
+        JOIN ParentCategories p ON p.parent_category_id = c.parent_category_id
+        WHERE c.child_category_id = $1"#,
+        child_category_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(category_type.into())
+}
+
+// 支出のカテゴリへの払い戻しのような逆向きのお金の動きは、種別を変えずに負の金額で記録する
+pub(crate) async fn ensure_category_type(
+    db_pool: &PgPool,
+    child_category_id: i32,
+    transaction_type: TransactionType,
+    field: impl Into<String>,
+) -> Result<(), ApiError> {
+    if fetch_category_type(db_pool, child_category_id).await? == transaction_type {
+        return Ok(());
+    }
+
+    Err(ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "category_type_mismatch",
+        format!(
+            "must be an {} category for an {} transaction; record money moving the other way as a negative amount",
+            transaction_type, transaction_type
+        ),
+    ).with_field(field))
+}
+
+async fn ensure_category_types(db_pool: &PgPool, transaction: &Transaction) -> Result<(), ApiError> {
+    if let Some(child_category_id) = transaction.child_category_id {
+        ensure_category_type(db_pool, child_category_id, transaction.transaction_type, "child_category_id").await?;
+    }
+
+    for (index, split) in transaction.splits.iter().enumerate() {
+        ensure_category_type(
+            db_pool,
+            split.child_category_id,
+            transaction.transaction_type,
+            format!("splits[{}].child_category_id", index),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+// 金額は取引の通貨 (省略時は口座の通貨) の補助単位まで。口座と異なる通貨の取引は、
+// 取引日の時点で口座の通貨へのレートが必要
+pub(crate) async fn ensure_currency<'e>(executor: impl PgExecutor<'e>, account_id: i32, transaction: &Transaction) -> Result<(), ApiError> {
+    let account = query!(
+        r#"SELECT currency, exchange_rate(COALESCE($1, currency), currency, $2, user_id) AS rate FROM Accounts WHERE account_id = $3"#,
+        transaction.currency,
+        transaction.transaction_date,
+        account_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    let currency = transaction.currency.as_deref().unwrap_or(&account.currency);
+    let splits = transaction
+        .splits
+        .iter()
+        .enumerate()
+        .map(|(index, split)| (format!("splits[{}].split_amount", index), &split.split_amount));
+    validate_minor_units(currency, std::iter::once(("transaction_amount".to_string(), &transaction.transaction_amount)).chain(splits))?;
+
+    match account.rate {
+        Some(_) => Ok(()),
+        None => Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "missing_exchange_rate",
+            format!("no exchange rate from {} to {} on or before {}", currency, account.currency, transaction.transaction_date),
+        ).with_field("currency")),
+    }
+}
+
+async fn insert_splits(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    transaction_id: i32,
+    splits: &[TransactionSplit],
+) -> Result<(), ApiError> {
+    for split in splits {
+        query!(
+            "INSERT INTO TransactionSplits (transaction_id, child_category_id, split_amount, split_memo) VALUES ($1, $2, $3, $4)",
+            transaction_id,
+            split.child_category_id,
+            split.split_amount.as_decimal(),
+            split.split_memo
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn fetch_transaction<'e>(executor: impl PgExecutor<'e>, transaction_id: i32) -> Result<Transaction, ApiError> {
+    let transaction = query_as!(
+        Transaction,
+        r#"SELECT transaction_id, account_id, child_category_id, transaction_amount,
+            transaction_type AS "transaction_type: TransactionType", transaction_date, transaction_description, currency, transfer_id,
+            transaction_splits_json(transaction_id) AS "splits!: sqlx::types::Json<Vec<TransactionSplit>>"
+        FROM Transactions
+        WHERE transaction_id = $1"#,
+        transaction_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(transaction)
+}
 
 pub async fn create_transaction(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
-    Json(transaction): Json<Transaction>
-) -> impl IntoResponse {
+    auth_user: AuthUser,
+    ValidatedJson(transaction): ValidatedJson<Transaction>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
-        Transaction,
-        "INSERT INTO Transactions (account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description) VALUES ($1, $2, $3, $4, $5, $6) RETURNING transaction_id, account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description",
+    ensure_account_owner(&db_pool, transaction.account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("account_id"))?;
+    ensure_categories_owner(&db_pool, &transaction, auth_user.user_id).await?;
+    ensure_category_types(&db_pool, &transaction).await?;
+    ensure_currency(&db_pool, transaction.account_id, &transaction).await?;
+
+    // 取引と分割行を 1 つのトランザクションで作成する
+    let mut tx = db_pool.begin().await?;
+
+    let transaction_id = query_scalar!(
+        "INSERT INTO Transactions (account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description, currency) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING transaction_id",
         transaction.account_id,
         transaction.child_category_id,
-        transaction.transaction_amount,
-        transaction.transaction_type,
+        transaction.transaction_amount.as_decimal(),
+        transaction.transaction_type as i32,
         transaction.transaction_date,
-        transaction.transaction_description
+        transaction.transaction_description,
+        transaction.currency
     )
-    .fetch_one(&db_pool)
-    .await
-    {
-        Ok(new_transaction) => (StatusCode::CREATED, Json(new_transaction)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .fetch_one(&mut *tx)
+    .await?;
+
+    insert_splits(&mut tx, transaction_id, &transaction.splits).await?;
+    record_budget_alerts(&mut *tx, &[transaction_id]).await?;
+
+    let new_transaction = fetch_transaction(&mut *tx, transaction_id).await?;
+
+    tx.commit().await?;
+    let currency = fetch_account_currency(&db_pool, new_transaction.account_id).await?;
+
+    Ok((StatusCode::CREATED, Json(new_transaction.in_currency(&currency))))
 }
 
 pub async fn get_transaction(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(transaction_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
-        Transaction,
-        "SELECT transaction_id, account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description FROM Transactions WHERE transaction_id = $1",
-        transaction_id
-    )
-    .fetch_one(&db_pool)
-    .await
-    {
-        Ok(transaction) => (StatusCode::OK, Json(transaction)).into_response(),
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
-    }
+    ensure_transaction_owner(&db_pool, transaction_id, auth_user.user_id).await?;
+
+    let transaction = fetch_transaction(&db_pool, transaction_id).await?;
+    let currency = fetch_account_currency(&db_pool, transaction.account_id).await?;
+
+    Ok((StatusCode::OK, Json(transaction.in_currency(&currency))))
 }
 
 pub async fn update_transaction(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(transaction_id): Path<i32>,
-    Json(transaction): Json<Transaction>
-) -> impl IntoResponse {
+    ValidatedJson(transaction): ValidatedJson<Transaction>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
-        Transaction,
-        "UPDATE Transactions SET transaction_amount = $1, transaction_type = $2, transaction_date = $3, transaction_description = $4 WHERE transaction_id = $5 RETURNING transaction_id, account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description",
-        transaction.transaction_amount,
-        transaction.transaction_type,
+    ensure_transaction_owner(&db_pool, transaction_id, auth_user.user_id).await?;
+    ensure_not_transfer(&db_pool, transaction_id).await?;
+    ensure_categories_owner(&db_pool, &transaction, auth_user.user_id).await?;
+    ensure_category_types(&db_pool, &transaction).await?;
+
+    let account_id = query_scalar!(
+        "SELECT account_id FROM Transactions WHERE transaction_id = $1",
+        transaction_id
+    )
+    .fetch_one(&db_pool)
+    .await?;
+    ensure_currency(&db_pool, account_id, &transaction).await?;
+
+    // 分割行は送られてきた内容で置き換える
+    let mut tx = db_pool.begin().await?;
+
+    query!(
+        "UPDATE Transactions SET child_category_id = $1, transaction_amount = $2, transaction_type = $3, transaction_date = $4, transaction_description = $5, currency = $6 WHERE transaction_id = $7",
+        transaction.child_category_id,
+        transaction.transaction_amount.as_decimal(),
+        transaction.transaction_type as i32,
         transaction.transaction_date,
         transaction.transaction_description,
+        transaction.currency,
         transaction_id
     )
-    .fetch_one(&db_pool)
-    .await
-    {
-        Ok(updated_transaction) => (StatusCode::OK, Json(updated_transaction)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .execute(&mut *tx)
+    .await?;
+
+    query!(
+        "DELETE FROM TransactionSplits WHERE transaction_id = $1",
+        transaction_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    insert_splits(&mut tx, transaction_id, &transaction.splits).await?;
+    record_budget_alerts(&mut *tx, &[transaction_id]).await?;
+
+    let updated_transaction = fetch_transaction(&mut *tx, transaction_id).await?;
+
+    tx.commit().await?;
+    let currency = fetch_account_currency(&db_pool, account_id).await?;
+
+    Ok((StatusCode::OK, Json(updated_transaction.in_currency(&currency))))
 }
 
 pub async fn delete_transaction(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(transaction_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query!(
+    ensure_transaction_owner(&db_pool, transaction_id, auth_user.user_id).await?;
+    ensure_not_transfer(&db_pool, transaction_id).await?;
+
+    query!(
         "DELETE FROM Transactions WHERE transaction_id = $1",
         transaction_id
     )
     .execute(&db_pool)
-    .await
-    {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// カーソルは最後に返した取引の (transaction_date, transaction_id) を符号化したもの
+fn encode_cursor(transaction_date: NaiveDate, transaction_id: i32) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}|{}", transaction_date, transaction_id))
+}
+
+fn decode_cursor(cursor: &str) -> Result<(NaiveDate, i32), ApiError> {
+    let invalid_cursor = || {
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_cursor", "the cursor is malformed")
+            .with_field("cursor")
+    };
+
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid_cursor())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid_cursor())?;
+    let (date, id) = decoded.split_once('|').ok_or_else(invalid_cursor)?;
+
+    Ok((
+        date.parse().map_err(|_| invalid_cursor())?,
+        id.parse().map_err(|_| invalid_cursor())?,
+    ))
+}
+
+// LIKE のワイルドカードをエスケープする
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+pub async fn list_account_transactions(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(account_id): Path<i32>,
+    ValidatedQuery(params): ValidatedQuery<TransactionListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, account_id, auth_user.user_id).await?;
+
+    let (cursor_date, cursor_id) = match params.cursor.as_deref() {
+        Some(cursor) => {
+            let (date, id) = decode_cursor(cursor)?;
+            (Some(date), Some(id))
+        }
+        None => (None, None),
+    };
+
+    // 次のページがあるか判定するため 1 件多く取得する
+    let mut transactions = query_as!(
+        Transaction,
+        r#"SELECT transaction_id, account_id, child_category_id, transaction_amount,
+            transaction_type AS "transaction_type: TransactionType", transaction_date, transaction_description, currency, transfer_id,
+            transaction_splits_json(transaction_id) AS "splits!: sqlx::types::Json<Vec<TransactionSplit>>"
+        FROM Transactions
+        WHERE account_id = $1
+            AND ($2::date IS NULL OR transaction_date >= $2)
+            AND ($3::date IS NULL OR transaction_date <= $3)
+            AND ($4::int IS NULL OR transaction_id IN (SELECT transaction_id FROM TransactionCategoryLines WHERE child_category_id = $4))
+            AND ($5::int IS NULL OR transaction_id IN (
+                SELECT l.transaction_id FROM TransactionCategoryLines l
+                JOIN ChildCategories c ON c.child_category_id = l.child_category_id
+                WHERE c.parent_category_id = $5
+            ))
+            AND ($6::int IS NULL OR transaction_type = $6)
+            AND ($7::numeric IS NULL OR transaction_amount >= $7)
+            AND ($8::numeric IS NULL OR transaction_amount <= $8)
+            AND ($9::text IS NULL OR transaction_description ILIKE '%' || $9 || '%')
+            AND ($10::date IS NULL OR (transaction_date, transaction_id) < ($10, $11))
+        ORDER BY transaction_date DESC, transaction_id DESC
+        LIMIT $12"#,
+        account_id,
+        params.from,
+        params.to,
+        params.child_category_id,
+        params.parent_category_id,
+        params.transaction_type.map(|transaction_type| transaction_type as i32),
+        params.min_amount.as_ref().map(Money::as_decimal),
+        params.max_amount.as_ref().map(Money::as_decimal),
+        params.description.as_deref().map(escape_like),
+        cursor_date,
+        cursor_id,
+        params.limit + 1
+    )
+    .fetch_all(&db_pool)
+    .await?;
+
+    let next_cursor = if transactions.len() as i64 > params.limit {
+        transactions.truncate(params.limit as usize);
+        transactions
+            .last()
+            .and_then(|last| last.transaction_id.map(|id| encode_cursor(last.transaction_date, id)))
+    } else {
+        None
+    };
+
+    let currency = fetch_account_currency(&db_pool, account_id).await?;
+
+    Ok((StatusCode::OK, Json(TransactionPage { transactions, next_cursor }.in_currency(&currency))))
 }
@@ -0,0 +1,195 @@
+use axum::{
+    extract::{Json, Extension, Path},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use sqlx::{query_as, query, query_scalar, PgExecutor};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::{ensure_account_owner, ensure_transfer_owner};
+use crate::db::AppState;
+use crate::error::ApiError;
+use crate::handlers::accounts::fetch_account_currency;
+use crate::models::money::InCurrency;
+use crate::models::transfer::Transfer;
+use crate::notifications::record_budget_alerts;
+use crate::validation::{validate_minor_units, ValidatedJson};
+
+async fn fetch_transfer<'e>(executor: impl PgExecutor<'e>, transfer_id: i32) -> Result<Transfer, ApiError> {
+    let transfer = query_as!(
+        Transfer,
+        r#"SELECT tr.transfer_id, tr.from_account_id, tr.to_account_id, tr.transfer_amount, tr.transfer_date, tr.transfer_description,
+            (SELECT transaction_id FROM Transactions WHERE transfer_id = tr.transfer_id AND transaction_type = 2) AS from_transaction_id,
+            (SELECT transaction_id FROM Transactions WHERE transfer_id = tr.transfer_id AND transaction_type = 1) AS to_transaction_id
+        FROM Transfers tr
+        WHERE tr.transfer_id = $1"#,
+        transfer_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(transfer)
+}
+
+// 振替は両方の口座に同じ金額を記録するため、通貨の異なる口座間では作成できない。共通の通貨を返す
+pub(crate) async fn ensure_same_currency<'e>(executor: impl PgExecutor<'e>, from_account_id: i32, to_account_id: i32) -> Result<String, ApiError> {
+    let accounts = query!(
+        r#"SELECT f.currency AS from_currency, t.currency AS to_currency
+        FROM Accounts f, Accounts t
+        WHERE f.account_id = $1 AND t.account_id = $2"#,
+        from_account_id,
+        to_account_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    if accounts.from_currency == accounts.to_currency {
+        Ok(accounts.from_currency)
+    } else {
+        Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "currency_mismatch",
+            "both accounts of a transfer must use the same currency",
+        ).with_field("to_account_id"))
+    }
+}
+
+pub async fn create_transfer(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    ValidatedJson(transfer): ValidatedJson<Transfer>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_account_owner(&db_pool, transfer.from_account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("from_account_id"))?;
+    ensure_account_owner(&db_pool, transfer.to_account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("to_account_id"))?;
+    let currency = ensure_same_currency(&db_pool, transfer.from_account_id, transfer.to_account_id).await?;
+    validate_minor_units(&currency, [("transfer_amount".to_string(), &transfer.transfer_amount)])?;
+
+    // 振替本体と出金・入金の取引を 1 つのトランザクションで作成する
+    let mut tx = db_pool.begin().await?;
+
+    let transfer_id = query_scalar!(
+        "INSERT INTO Transfers (from_account_id, to_account_id, transfer_amount, transfer_date, transfer_description) VALUES ($1, $2, $3, $4, $5) RETURNING transfer_id",
+        transfer.from_account_id,
+        transfer.to_account_id,
+        transfer.transfer_amount.as_decimal(),
+        transfer.transfer_date,
+        transfer.transfer_description
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let transaction_ids = query_scalar!(
+        "INSERT INTO Transactions (account_id, transaction_amount, transaction_type, transaction_date, transaction_description, transfer_id) VALUES ($1, $3, 2, $4, $5, $6), ($2, $3, 1, $4, $5, $6) RETURNING transaction_id",
+        transfer.from_account_id,
+        transfer.to_account_id,
+        transfer.transfer_amount.as_decimal(),
+        transfer.transfer_date,
+        transfer.transfer_description,
+        transfer_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    record_budget_alerts(&mut *tx, &transaction_ids).await?;
+
+    let new_transfer = fetch_transfer(&mut *tx, transfer_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(new_transfer.in_currency(&currency))))
+}
+
+pub async fn get_transfer(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(transfer_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_transfer_owner(&db_pool, transfer_id, auth_user.user_id).await?;
+
+    let transfer = fetch_transfer(&db_pool, transfer_id).await?;
+    let currency = fetch_account_currency(&db_pool, transfer.from_account_id).await?;
+
+    Ok((StatusCode::OK, Json(transfer.in_currency(&currency))))
+}
+
+pub async fn update_transfer(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(transfer_id): Path<i32>,
+    ValidatedJson(transfer): ValidatedJson<Transfer>
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_transfer_owner(&db_pool, transfer_id, auth_user.user_id).await?;
+    ensure_account_owner(&db_pool, transfer.from_account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("from_account_id"))?;
+    ensure_account_owner(&db_pool, transfer.to_account_id, auth_user.user_id)
+        .await
+        .map_err(|e| e.into_invalid_reference("to_account_id"))?;
+    let currency = ensure_same_currency(&db_pool, transfer.from_account_id, transfer.to_account_id).await?;
+    validate_minor_units(&currency, [("transfer_amount".to_string(), &transfer.transfer_amount)])?;
+
+    let mut tx = db_pool.begin().await?;
+
+    query!(
+        "UPDATE Transfers SET from_account_id = $1, to_account_id = $2, transfer_amount = $3, transfer_date = $4, transfer_description = $5 WHERE transfer_id = $6",
+        transfer.from_account_id,
+        transfer.to_account_id,
+        transfer.transfer_amount.as_decimal(),
+        transfer.transfer_date,
+        transfer.transfer_description,
+        transfer_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // 口座が変わった場合は出金・入金の取引もそれぞれの口座に移す
+    let transaction_ids = query_scalar!(
+        "UPDATE Transactions SET account_id = CASE transaction_type WHEN 2 THEN $1::int ELSE $2::int END, transaction_amount = $3, transaction_date = $4, transaction_description = $5 WHERE transfer_id = $6
+        RETURNING transaction_id",
+        transfer.from_account_id,
+        transfer.to_account_id,
+        transfer.transfer_amount.as_decimal(),
+        transfer.transfer_date,
+        transfer.transfer_description,
+        transfer_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    record_budget_alerts(&mut *tx, &transaction_ids).await?;
+
+    let updated_transfer = fetch_transfer(&mut *tx, transfer_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(updated_transfer.in_currency(&currency))))
+}
+
+pub async fn delete_transfer(
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
+    Path(transfer_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let db_pool = state.lock().await.db_pool.clone();
+
+    ensure_transfer_owner(&db_pool, transfer_id, auth_user.user_id).await?;
+
+    // 出金・入金の取引は ON DELETE CASCADE で同時に削除される
+    query!(
+        "DELETE FROM Transfers WHERE transfer_id = $1",
+        transfer_id
+    )
+    .execute(&db_pool)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
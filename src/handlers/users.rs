@@ -3,112 +3,130 @@ use axum::{
     response::IntoResponse,
     http::StatusCode,
 };
-use sqlx::{query_as, query};
+use sqlx::{query_as, query, query_scalar, PgExecutor};
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use crate::auth::extractor::AuthUser;
+use crate::auth::ownership::ensure_user;
 use crate::db::AppState;
-use crate::models::user::User;
+use crate::auth::password::hash_password;
+use crate::error::ApiError;
+use crate::models::user::{User, UserInput};
+use crate::validation::ValidatedJson;
+
+// 予算とレポートの金額に使うユーザーの基準通貨
+pub(crate) async fn fetch_base_currency<'e>(executor: impl PgExecutor<'e>, user_id: i32) -> Result<String, ApiError> {
+    let currency = query_scalar!("SELECT base_currency FROM Users WHERE user_id = $1", user_id)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(currency)
+}
 
 pub async fn create_user(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
-    Json(user): Json<User>
-) -> impl IntoResponse {
+    ValidatedJson(user): ValidatedJson<UserInput>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    let password_hash = hash_password(&user.user_password)
+        .map_err(|e| ApiError::internal("Failed to hash password", e))?;
+
+    let new_user = query_as!(
         User,
-        "INSERT INTO Users (username, user_email, user_password) VALUES ($1, $2, $3) RETURNING user_id, username, user_email, user_password, created_at",
+        "INSERT INTO Users (username, user_email, user_password, base_currency) VALUES ($1, $2, $3, COALESCE($4, 'USD')) RETURNING user_id, username, user_email, base_currency, created_at",
         user.username,
         user.user_email,
-        user.user_password
+        password_hash,
+        user.base_currency
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(new_user) => (StatusCode::CREATED, Json(new_user)).into_response(),
-        Err(e) => {
-            eprintln!("Failed to create user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
-        }
-    }
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(new_user)))
 }
 
 pub async fn get_user(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(user_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_user(user_id, auth_user.user_id)?;
+
+    let user = query_as!(
         User,
-        "SELECT user_id, username, user_email, user_password, created_at FROM Users WHERE user_id = $1",
+        "SELECT user_id, username, user_email, base_currency, created_at FROM Users WHERE user_id = $1",
         user_id
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::OK, Json(user)))
 }
 
 pub async fn get_users(
-    Extension(state): Extension<Arc<Mutex<AppState>>>
-) -> impl IntoResponse {
+    Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    // 他のユーザーの情報は返さない
+    let users = query_as!(
         User,
-        "SELECT user_id, username, user_email, user_password, created_at FROM Users"
+        "SELECT user_id, username, user_email, base_currency, created_at FROM Users WHERE user_id = $1",
+        auth_user.user_id
     )
     .fetch_all(&db_pool)
-    .await
-    {
-        Ok(users) => (StatusCode::OK, Json(users)).into_response(),
-        Err(e) => {
-            eprintln!("Failed to fetch users: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
-        }
-    }
+    .await?;
+
+    Ok((StatusCode::OK, Json(users)))
 }
 
 pub async fn update_user(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(user_id): Path<i32>,
-    Json(user): Json<User>
-) -> impl IntoResponse {
+    ValidatedJson(user): ValidatedJson<UserInput>
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query_as!(
+    ensure_user(user_id, auth_user.user_id)?;
+
+    let password_hash = hash_password(&user.user_password)
+        .map_err(|e| ApiError::internal("Failed to hash password", e))?;
+
+    let updated_user = query_as!(
         User,
-        "UPDATE Users SET username = $1, user_email = $2, user_password = $3 WHERE user_id = $4 RETURNING user_id, username, user_email, user_password, created_at",
+        "UPDATE Users SET username = $1, user_email = $2, user_password = $3, base_currency = COALESCE($4, base_currency) WHERE user_id = $5 RETURNING user_id, username, user_email, base_currency, created_at",
         user.username,
         user.user_email,
-        user.user_password,
+        password_hash,
+        user.base_currency,
         user_id
     )
     .fetch_one(&db_pool)
-    .await
-    {
-        Ok(updated_user) => (StatusCode::OK, Json(updated_user)).into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok((StatusCode::OK, Json(updated_user)))
 }
 
 pub async fn delete_user(
     Extension(state): Extension<Arc<Mutex<AppState>>>,
+    auth_user: AuthUser,
     Path(user_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ApiError> {
     let db_pool = state.lock().await.db_pool.clone();
 
-    match query!(
+    ensure_user(user_id, auth_user.user_id)?;
+
+    query!(
         "DELETE FROM Users WHERE user_id = $1",
         user_id
     )
     .execute(&db_pool)
-    .await
-    {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
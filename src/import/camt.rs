@@ -0,0 +1,278 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::str::FromStr;
+use crate::error::ApiError;
+use crate::models::money::Money;
+use crate::models::transaction::TransactionType;
+use super::{check_amount, LineCounter, ParsedRow, RowError, StatementRow};
+
+// 明細の 1 エントリ (Ntry) から集めた値
+#[derive(Default)]
+struct Entry {
+    line: u64,
+    amount: Option<String>,
+    currency: Option<String>,
+    credit_debit: Option<String>,
+    status: Option<String>,
+    booking_date: Option<String>,
+    value_date: Option<String>,
+    reference: Option<String>,
+    entry_reference: Option<String>,
+    additional_info: Option<String>,
+    remittance_info: Option<String>,
+    counterparty: Option<String>,
+}
+
+fn invalid_camt(message: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_camt", message)
+}
+
+// Ntry から見た要素のパスに応じて値を振り分ける。同じ項目が複数ある場合は最初の値を使う
+fn assign(entry: &mut Entry, path: &[String], value: String) {
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+    let slot = match path.as_slice() {
+        ["Amt"] => &mut entry.amount,
+        ["CdtDbtInd"] => &mut entry.credit_debit,
+        ["Sts"] | ["Sts", "Cd"] => &mut entry.status,
+        ["BookgDt", "Dt" | "DtTm"] => &mut entry.booking_date,
+        ["ValDt", "Dt" | "DtTm"] => &mut entry.value_date,
+        ["AcctSvcrRef"] => &mut entry.reference,
+        ["NtryRef"] => &mut entry.entry_reference,
+        ["AddtlNtryInf"] => &mut entry.additional_info,
+        ["NtryDtls", "TxDtls", "RmtInf", "Ustrd"] => &mut entry.remittance_info,
+        ["NtryDtls", "TxDtls", "RltdPties", "Cdtr" | "Dbtr", "Nm"]
+        | ["NtryDtls", "TxDtls", "RltdPties", "Cdtr" | "Dbtr", "Pty", "Nm"] => &mut entry.counterparty,
+        _ => return,
+    };
+    if slot.is_none() {
+        *slot = Some(value);
+    }
+}
+
+fn parse_entry(entry: Entry, account_currency: &str) -> Option<ParsedRow> {
+    let line = entry.line;
+    let row_error = |message: String| RowError { line, message };
+
+    // 予約中 (PDNG) や参考情報 (INFO) のエントリは取り込まない
+    if entry.status.as_deref().is_some_and(|status| status != "BOOK") {
+        return None;
+    }
+
+    let parse = || {
+        let date_value = entry
+            .booking_date
+            .or(entry.value_date)
+            .ok_or_else(|| row_error("BookgDt is missing".to_string()))?;
+        let transaction_date = date_value
+            .get(..10)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .ok_or_else(|| row_error(format!("\"{}\" is not a valid BookgDt", date_value)))?;
+
+        let amount_value = entry.amount.ok_or_else(|| row_error("Amt is missing".to_string()))?;
+        let transaction_amount = Money::from_str(&amount_value)
+            .ok()
+            .filter(Money::is_positive)
+            .ok_or_else(|| row_error(format!("\"{}\" is not a valid Amt", amount_value)))?;
+        check_amount(&transaction_amount).map_err(row_error)?;
+        // 取引は口座の通貨で登録するため、別の通貨のエントリは取り込まない
+        match entry.currency.as_deref() {
+            Some(currency) if currency != account_currency => {
+                return Err(row_error(format!("the Amt currency {} does not match the account currency {}", currency, account_currency)));
+            }
+            Some(_) => {}
+            None => return Err(row_error("the Ccy of Amt is missing".to_string())),
+        }
+
+        let transaction_type = match entry.credit_debit.as_deref() {
+            Some("CRDT") => TransactionType::Income,
+            Some("DBIT") => TransactionType::Expense,
+            Some(other) => return Err(row_error(format!("\"{}\" is not a valid CdtDbtInd", other))),
+            None => return Err(row_error("CdtDbtInd is missing".to_string())),
+        };
+
+        let transaction_description = entry.remittance_info.or(entry.additional_info).or(entry.counterparty);
+        let external_id = entry.reference.or(entry.entry_reference);
+
+        Ok(StatementRow { line, transaction_date, transaction_amount, transaction_type, transaction_description, external_id })
+    };
+
+    Some(parse())
+}
+
+// ISO 20022 camt.053 (銀行取引明細) の Stmt/Ntry を取り出す。名前空間の接頭辞は無視する。
+// account_currency は取り込み先の口座の通貨で、Amt の通貨 (Ccy) がこれと異なるエントリは無効な行になる
+pub fn parse(data: &str, account_currency: &str) -> Result<Vec<ParsedRow>, ApiError> {
+    let mut reader = Reader::from_str(data);
+    reader.config_mut().trim_text(true);
+
+    let mut lines = LineCounter::new(data);
+
+    let mut stack: Vec<String> = Vec::new();
+    let mut entry: Option<(usize, Entry)> = None;
+    let mut found_statement = false;
+    let mut rows = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| invalid_camt(format!("line {}: {}", lines.line_at(reader.error_position() as usize), e)))?;
+
+        match event {
+            Event::Start(start) => {
+                let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "BkToCstmrStmt" => found_statement = true,
+                    "Ntry" if entry.is_none() => {
+                        let line = lines.line_at(reader.buffer_position() as usize);
+                        entry = Some((stack.len() + 1, Entry { line, ..Entry::default() }));
+                    }
+                    // Ntry 直下の Amt の通貨。TxDtls の中の金額は見ない
+                    "Amt" => {
+                        if let Some((_, current)) = entry.as_mut().filter(|(depth, _)| *depth == stack.len()) {
+                            let currency = start
+                                .try_get_attribute("Ccy")
+                                .map_err(|e| invalid_camt(format!("line {}: {}", lines.line_at(reader.buffer_position() as usize), e)))?;
+                            if let Some(currency) = currency {
+                                let currency = currency.unescape_value().map_err(|e| invalid_camt(e.to_string()))?;
+                                current.currency.get_or_insert(currency.into_owned());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                stack.push(name);
+            }
+            Event::End(_) => {
+                if entry.as_ref().is_some_and(|(depth, _)| *depth == stack.len()) {
+                    if let Some((_, finished)) = entry.take() {
+                        rows.extend(parse_entry(finished, account_currency));
+                    }
+                }
+                stack.pop();
+            }
+            Event::Text(text) => {
+                if let Some((depth, current)) = entry.as_mut() {
+                    let value = text.unescape().map_err(|e| invalid_camt(e.to_string()))?;
+                    assign(current, &stack[*depth..], value.into_owned());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !found_statement {
+        return Err(invalid_camt("the file is not a camt.053 statement"));
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(value: &str) -> Money {
+        Money::from_str(value).unwrap()
+    }
+
+    fn statement(entries: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+<BkToCstmrStmt>
+<Stmt>
+{}
+</Stmt>
+</BkToCstmrStmt>
+</Document>"#,
+            entries
+        )
+    }
+
+    #[test]
+    fn parse_reads_booked_entries() {
+        let data = statement(
+            r#"<Ntry>
+<Amt Ccy="EUR">42.10</Amt>
+<CdtDbtInd>DBIT</CdtDbtInd>
+<Sts>BOOK</Sts>
+<BookgDt><Dt>2024-04-01</Dt></BookgDt>
+<AcctSvcrRef>REF-1</AcctSvcrRef>
+<NtryDtls><TxDtls>
+<AmtDtls><TxAmt><Amt Ccy="USD">45.00</Amt></TxAmt></AmtDtls>
+<RmtInf><Ustrd>Groceries</Ustrd></RmtInf>
+</TxDtls></NtryDtls>
+</Ntry>
+<Ntry>
+<Amt Ccy="EUR">1500</Amt>
+<CdtDbtInd>CRDT</CdtDbtInd>
+<Sts><Cd>BOOK</Cd></Sts>
+<ValDt><DtTm>2024-04-25T09:00:00</DtTm></ValDt>
+<NtryRef>E-2</NtryRef>
+<NtryDtls><TxDtls><RltdPties><Dbtr><Nm>Employer</Nm></Dbtr></RltdPties></TxDtls></NtryDtls>
+</Ntry>"#,
+        );
+        let rows = parse(&data, "EUR").unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let first = rows[0].as_ref().ok().unwrap();
+        assert_eq!(first.line, 5);
+        assert_eq!(first.transaction_date, NaiveDate::from_ymd_opt(2024, 4, 1).unwrap());
+        assert_eq!(first.transaction_amount, money("42.10"));
+        assert_eq!(first.transaction_type, TransactionType::Expense);
+        assert_eq!(first.transaction_description.as_deref(), Some("Groceries"));
+        assert_eq!(first.external_id.as_deref(), Some("REF-1"));
+
+        let second = rows[1].as_ref().ok().unwrap();
+        assert_eq!(second.line, 16);
+        assert_eq!(second.transaction_date, NaiveDate::from_ymd_opt(2024, 4, 25).unwrap());
+        assert_eq!(second.transaction_type, TransactionType::Income);
+        assert_eq!(second.transaction_description.as_deref(), Some("Employer"));
+        assert_eq!(second.external_id.as_deref(), Some("E-2"));
+    }
+
+    #[test]
+    fn parse_skips_pending_entries() {
+        let data = statement(
+            r#"<Ntry><Amt Ccy="EUR">10</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>PDNG</Sts><BookgDt><Dt>2024-04-01</Dt></BookgDt></Ntry>
+<Ntry><Amt Ccy="EUR">20</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>INFO</Sts><BookgDt><Dt>2024-04-01</Dt></BookgDt></Ntry>"#,
+        );
+        assert!(parse(&data, "EUR").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_entries_in_another_currency() {
+        let data = statement(
+            r#"<Ntry><Amt Ccy="USD">10</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>BOOK</Sts><BookgDt><Dt>2024-04-01</Dt></BookgDt></Ntry>
+<Ntry><Amt>10</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>BOOK</Sts><BookgDt><Dt>2024-04-01</Dt></BookgDt></Ntry>"#,
+        );
+        let errors: Vec<_> = parse(&data, "EUR").unwrap().into_iter().map(|row| row.err().unwrap()).collect();
+        assert_eq!(errors[0].line, 5);
+        assert_eq!(errors[0].message, "the Amt currency USD does not match the account currency EUR");
+        assert_eq!(errors[1].line, 6);
+        assert_eq!(errors[1].message, "the Ccy of Amt is missing");
+    }
+
+    #[test]
+    fn parse_reports_invalid_entries() {
+        let data = statement(
+            r#"<Ntry><Amt Ccy="EUR">-5</Amt><CdtDbtInd>DBIT</CdtDbtInd><BookgDt><Dt>2024-04-01</Dt></BookgDt></Ntry>
+<Ntry><Amt Ccy="EUR">5</Amt><CdtDbtInd>XXXX</CdtDbtInd><BookgDt><Dt>2024-04-01</Dt></BookgDt></Ntry>
+<Ntry><Amt Ccy="EUR">5</Amt><CdtDbtInd>DBIT</CdtDbtInd></Ntry>"#,
+        );
+        let messages: Vec<_> = parse(&data, "EUR").unwrap().into_iter().map(|row| row.err().unwrap().message).collect();
+        assert_eq!(
+            messages,
+            vec!["\"-5\" is not a valid Amt", "\"XXXX\" is not a valid CdtDbtInd", "BookgDt is missing"]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_other_documents() {
+        assert!(parse("<Document><BkToCstmrDbtCdtNtfctn/></Document>", "EUR").is_err());
+        assert!(parse(&statement("<Ntry><Amt>"), "EUR").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use std::str::FromStr;
+use crate::error::ApiError;
+use crate::models::import::ImportMapping;
+use crate::models::money::Money;
+use super::{split_signed_amount, LineCounter, ParsedRow, RowError, StatementRow};
+
+// 金額から桁区切りと通貨記号を取り除く。括弧で囲まれた金額は負の値とみなす
+fn parse_amount(value: &str) -> Option<Money> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, ',' | '¥' | '￥' | '円' | '$' | '€' | '£'))
+        .collect();
+
+    match cleaned.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')) {
+        Some(inner) => Money::from_str(inner).ok().map(|amount| -amount),
+        None => Money::from_str(&cleaned).ok(),
+    }
+}
+
+fn column_index(headers: &::csv::StringRecord, column: &str, field: &'static str) -> Result<usize, ApiError> {
+    headers
+        .iter()
+        .position(|header| header.trim() == column)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "missing_column",
+                format!("the CSV has no column named \"{}\"", column),
+            )
+            .with_field(field)
+        })
+}
+
+// 見出し行付きの CSV を保存済みの対応付けに従って読み込む
+pub fn parse(mapping: &ImportMapping, data: &str) -> Result<Vec<ParsedRow>, ApiError> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_csv", e.to_string()))?
+        .clone();
+
+    let date_index = column_index(&headers, &mapping.date_column, "date_column")?;
+    let amount_index = column_index(&headers, &mapping.amount_column, "amount_column")?;
+    let description_index = match mapping.description_column.as_deref() {
+        Some(column) => Some(column_index(&headers, column, "description_column")?),
+        None => None,
+    };
+
+    // csv の行番号は読み飛ばした空行を数えず、行の位置は空行の前を指すため、空行を除いた位置から数える
+    let mut lines = LineCounter::new(data);
+    let mut line_of = |position: &::csv::Position| {
+        let skipped = data.as_bytes()[position.byte() as usize..].iter().take_while(|byte| matches!(byte, b'\r' | b'\n')).count();
+        lines.line_at(position.byte() as usize + skipped)
+    };
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, &mut line_of);
+                rows.push(Err(RowError { line, message: e.to_string() }));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, &mut line_of);
+
+        // 空行は読み飛ばす
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        rows.push(parse_record(mapping, &record, line, date_index, amount_index, description_index));
+    }
+
+    Ok(rows)
+}
+
+fn parse_record(
+    mapping: &ImportMapping,
+    record: &::csv::StringRecord,
+    line: u64,
+    date_index: usize,
+    amount_index: usize,
+    description_index: Option<usize>,
+) -> ParsedRow {
+    let row_error = |message: String| RowError { line, message };
+
+    let date_value = record.get(date_index).unwrap_or_default().trim();
+    let transaction_date = NaiveDate::parse_from_str(date_value, &mapping.date_format)
+        .map_err(|_| row_error(format!("\"{}\" does not match the date format {}", date_value, mapping.date_format)))?;
+
+    let amount_value = record.get(amount_index).unwrap_or_default();
+    let amount = parse_amount(amount_value)
+        .ok_or_else(|| row_error(format!("\"{}\" is not a valid amount", amount_value.trim())))?;
+    let (transaction_amount, transaction_type) = split_signed_amount(amount, mapping.amount_sign).map_err(row_error)?;
+
+    let transaction_description = description_index
+        .and_then(|index| record.get(index))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+
+    Ok(StatementRow { line, transaction_date, transaction_amount, transaction_type, transaction_description, external_id: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::import::AmountSign;
+    use crate::models::transaction::TransactionType;
+
+    fn mapping(date_format: &str, amount_sign: AmountSign) -> ImportMapping {
+        ImportMapping {
+            mapping_id: Some(1),
+            account_id: 1,
+            mapping_name: "bank".to_string(),
+            date_column: "日付".to_string(),
+            date_format: date_format.to_string(),
+            amount_column: "金額".to_string(),
+            amount_sign,
+            description_column: Some("摘要".to_string()),
+        }
+    }
+
+    fn money(value: &str) -> Money {
+        Money::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn parse_amount_strips_separators_and_currency_symbols() {
+        assert_eq!(parse_amount("1,234"), Some(money("1234")));
+        assert_eq!(parse_amount(" ¥12,000 "), Some(money("12000")));
+        assert_eq!(parse_amount("3,500円"), Some(money("3500")));
+        assert_eq!(parse_amount("-$12.50"), Some(money("-12.50")));
+        assert_eq!(parse_amount("(1,000)"), Some(money("-1000")));
+        assert_eq!(parse_amount("abc"), None);
+        assert_eq!(parse_amount(""), None);
+    }
+
+    #[test]
+    fn parse_reads_rows_with_date_format_and_amount_sign() {
+        let data = "日付,金額,摘要\n2024/04/01,-1200,  コーヒー \n2024/04/25,250000,給与\n";
+        let rows = parse(&mapping("%Y/%m/%d", AmountSign::NegativeExpense), data).unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let first = rows[0].as_ref().ok().unwrap();
+        assert_eq!(first.line, 2);
+        assert_eq!(first.transaction_date, NaiveDate::from_ymd_opt(2024, 4, 1).unwrap());
+        assert_eq!(first.transaction_amount, money("1200"));
+        assert_eq!(first.transaction_type, TransactionType::Expense);
+        assert_eq!(first.transaction_description.as_deref(), Some("コーヒー"));
+        assert_eq!(first.external_id, None);
+
+        let second = rows[1].as_ref().ok().unwrap();
+        assert_eq!(second.line, 3);
+        assert_eq!(second.transaction_type, TransactionType::Income);
+    }
+
+    #[test]
+    fn parse_treats_positive_amounts_as_expenses_when_configured() {
+        let data = "日付,金額,摘要\n01.04.2024,1200,card\n02.04.2024,-300,refund\n";
+        let rows = parse(&mapping("%d.%m.%Y", AmountSign::PositiveExpense), data).unwrap();
+        let types: Vec<_> = rows.iter().map(|row| row.as_ref().ok().unwrap().transaction_type).collect();
+        assert_eq!(types, vec![TransactionType::Expense, TransactionType::Income]);
+        assert_eq!(rows[1].as_ref().ok().unwrap().transaction_amount, money("300"));
+    }
+
+    #[test]
+    fn parse_reports_invalid_rows_with_line_numbers() {
+        let data = "日付,金額,摘要\n2024-04-01,100,ok\n\n04/02/2024,100,bad date\n2024-04-03,abc,bad amount\n2024-04-04,0,zero\n";
+        let rows = parse(&mapping("%Y-%m-%d", AmountSign::NegativeExpense), data).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].is_ok());
+
+        let errors: Vec<_> = rows[1..].iter().map(|row| row.as_ref().err().unwrap()).collect();
+        assert_eq!(errors[0].line, 4);
+        assert_eq!(errors[0].message, "\"04/02/2024\" does not match the date format %Y-%m-%d");
+        assert_eq!(errors[1].line, 5);
+        assert_eq!(errors[1].message, "\"abc\" is not a valid amount");
+        assert_eq!(errors[2].line, 6);
+        assert_eq!(errors[2].message, "the amount must not be zero");
+    }
+
+    #[test]
+    fn parse_rejects_missing_columns() {
+        let data = "日付,金額\n2024-04-01,100\n";
+        let error = format!("{:?}", parse(&mapping("%Y-%m-%d", AmountSign::NegativeExpense), data).err().unwrap());
+        assert!(error.contains("code: \"missing_column\""));
+        assert!(error.contains("field: Some(\"description_column\")"));
+    }
+}
@@ -0,0 +1,539 @@
+pub mod camt;
+pub mod csv;
+pub mod ofx;
+
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+use sqlx::{query, query_as, query_scalar, PgPool};
+use std::collections::{HashMap, HashSet};
+use crate::error::ApiError;
+use crate::handlers::transactions::fetch_category_type;
+use crate::models::import::{AmountSign, ImportResult, ImportRowResult, ImportRowStatus};
+use crate::models::money::{amount_limits, minor_units, Money};
+use crate::models::transaction::TransactionType;
+use crate::notifications::record_budget_alerts;
+
+// 明細から読み取った 1 行。金額は正の値で、口座への入出金の向きは transaction_type に反映済み
+pub struct StatementRow {
+    pub line: u64,
+    pub transaction_date: NaiveDate,
+    pub transaction_amount: Money,
+    pub transaction_type: TransactionType,
+    pub transaction_description: Option<String>,
+    // 銀行側の取引 ID。CSV にはない
+    pub external_id: Option<String>,
+}
+
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+pub type ParsedRow = Result<StatementRow, RowError>;
+
+// バイト位置を 1 始まりの行番号に変換する。位置は先頭から順に尋ねられるため、前回の位置からの改行だけを数える
+pub struct LineCounter<'a> {
+    data: &'a [u8],
+    offset: usize,
+    line: u64,
+}
+
+impl<'a> LineCounter<'a> {
+    pub fn new(data: &'a str) -> Self {
+        LineCounter { data: data.as_bytes(), offset: 0, line: 1 }
+    }
+
+    pub fn line_at(&mut self, offset: usize) -> u64 {
+        let offset = offset.min(self.data.len());
+        if offset < self.offset {
+            self.offset = 0;
+            self.line = 1;
+        }
+        // 改行はマルチバイト文字の途中に現れないため、文字の境界でない位置でもバイト単位で数えられる
+        self.line += self.data[self.offset..offset].iter().filter(|byte| **byte == b'\n').count() as u64;
+        self.offset = offset;
+        self.line
+    }
+}
+
+// 金額の列に保存できない金額の行は取り込まない
+pub fn check_amount(amount: &Money) -> Result<(), String> {
+    if amount.fits_column() {
+        Ok(())
+    } else {
+        Err(format!("the amount {} {}", amount.as_decimal(), amount_limits(Money::SCALE)))
+    }
+}
+
+pub fn split_signed_amount(amount: Money, sign: AmountSign) -> Result<(Money, TransactionType), String> {
+    if amount == Money::zero() {
+        return Err("the amount must not be zero".to_string());
+    }
+    check_amount(&amount)?;
+
+    let negative = amount.is_negative();
+    let transaction_type = match (sign, negative) {
+        (AmountSign::NegativeExpense, true) | (AmountSign::PositiveExpense, false) => TransactionType::Expense,
+        _ => TransactionType::Income,
+    };
+
+    Ok((amount.abs(), transaction_type))
+}
+
+// 取引の種別はカテゴリの種別に合わせるため、登録済みの負の金額の取引は逆向きの明細の行と照合する
+fn statement_amount(transaction_type: TransactionType, transaction_amount: Money) -> (Money, TransactionType) {
+    if transaction_amount.is_negative() {
+        (-transaction_amount, transaction_type.opposite())
+    } else {
+        (transaction_amount, transaction_type)
+    }
+}
+
+impl StatementRow {
+    // カテゴリの種別の取引として登録する金額。支出のカテゴリへの入金は負の金額の支出になる
+    fn categorized_amount(&self, category_type: TransactionType) -> Money {
+        if self.transaction_type == category_type {
+            self.transaction_amount.clone()
+        } else {
+            -self.transaction_amount.clone()
+        }
+    }
+}
+
+// 日付・金額・種別・摘要から重複判定用の値を作る。摘要は空白の違いと大文字小文字を無視する
+pub fn fingerprint(
+    transaction_date: NaiveDate,
+    transaction_amount: &Money,
+    transaction_type: TransactionType,
+    transaction_description: Option<&str>,
+) -> String {
+    let description = transaction_description
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    let digest = Sha256::digest(format!(
+        "{}|{}|{}|{}",
+        transaction_date,
+        transaction_amount,
+        transaction_type,
+        description
+    ));
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn invalid_row(error: RowError) -> ImportRowResult {
+    ImportRowResult {
+        line: error.line,
+        status: ImportRowStatus::Invalid,
+        transaction_date: None,
+        transaction_amount: None,
+        transaction_type: None,
+        transaction_description: None,
+        message: Some(error.message),
+    }
+}
+
+// 重複判定に使う登録済みの取引
+struct ExistingTransaction {
+    transaction_date: NaiveDate,
+    transaction_amount: Money,
+    transaction_type: TransactionType,
+    transaction_description: Option<String>,
+    external_id: Option<String>,
+}
+
+// 口座の通貨の補助単位より細かい金額の行は取り込まない
+async fn classify(
+    db_pool: &PgPool,
+    account_id: i32,
+    category_type: TransactionType,
+    rows: Vec<ParsedRow>,
+) -> Result<Vec<(ImportRowResult, Option<StatementRow>)>, ApiError> {
+    let currency = query_scalar!("SELECT currency FROM Accounts WHERE account_id = $1", account_id)
+        .fetch_one(db_pool)
+        .await?;
+    let rows: Vec<ParsedRow> = rows
+        .into_iter()
+        .map(|row| match row {
+            Ok(row) if !row.transaction_amount.fits_currency(&currency) => Err(RowError {
+                line: row.line,
+                message: format!("the amount {} {} for {}", row.transaction_amount.as_decimal(), amount_limits(minor_units(&currency)), currency),
+            }),
+            row => row,
+        })
+        .collect();
+
+    let dates = rows.iter().filter_map(|row| row.as_ref().ok()).map(|row| row.transaction_date);
+    let (from, to) = match (dates.clone().min(), dates.max()) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Ok(rows.into_iter().filter_map(Result::err).map(|e| (invalid_row(e), None)).collect()),
+    };
+
+    let existing = query_as!(
+        ExistingTransaction,
+        r#"SELECT transaction_date, transaction_amount AS "transaction_amount: Money", transaction_type AS "transaction_type: TransactionType",
+            transaction_description, external_id
+        FROM Transactions
+        WHERE account_id = $1 AND transaction_date BETWEEN $2 AND $3"#,
+        account_id,
+        from,
+        to
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let external_ids: Vec<String> = rows
+        .iter()
+        .filter_map(|row| row.as_ref().ok())
+        .filter_map(|row| row.external_id.clone())
+        .collect();
+    // 取引 ID は日付が変わることがあるため期間を絞らずに照合する
+    let seen_external_ids: HashSet<String> = query_scalar!(
+        r#"SELECT external_id AS "external_id!" FROM Transactions WHERE account_id = $1 AND external_id = ANY($2)"#,
+        account_id,
+        &external_ids
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    Ok(classify_rows(category_type, rows, existing, seen_external_ids))
+}
+
+// 銀行側の取引 ID が既存の取引と一致する行と、既存の取引と同じ内容の行を重複とする。
+// 同じ内容の行が複数ある場合は既存の件数を超えた分だけを新しい行として扱う。
+// 取引 ID のある行は、ID が異なる既存の取引とは内容が同じでも別の取引とみなす
+fn classify_rows(
+    category_type: TransactionType,
+    rows: Vec<ParsedRow>,
+    existing: Vec<ExistingTransaction>,
+    mut seen_external_ids: HashSet<String>,
+) -> Vec<(ImportRowResult, Option<StatementRow>)> {
+    // すべての既存の取引と、取引 ID のない既存の取引のそれぞれで件数を数える
+    let mut existing_counts: HashMap<String, usize> = HashMap::new();
+    let mut unidentified_counts: HashMap<String, usize> = HashMap::new();
+    for transaction in existing {
+        let (amount, transaction_type) = statement_amount(transaction.transaction_type, transaction.transaction_amount);
+        let key = fingerprint(
+            transaction.transaction_date,
+            &amount,
+            transaction_type,
+            transaction.transaction_description.as_deref(),
+        );
+        if transaction.external_id.is_none() {
+            *unidentified_counts.entry(key.clone()).or_default() += 1;
+        }
+        *existing_counts.entry(key).or_default() += 1;
+    }
+
+    rows.into_iter()
+        .map(|row| match row {
+            Ok(row) => {
+                let key = fingerprint(
+                    row.transaction_date,
+                    &row.transaction_amount,
+                    row.transaction_type,
+                    row.transaction_description.as_deref(),
+                );
+                let counts = match row.external_id {
+                    Some(_) => &mut unidentified_counts,
+                    None => &mut existing_counts,
+                };
+                let status = match (&row.external_id, counts.get_mut(&key)) {
+                    // 同じファイル内で繰り返された ID も重複とする
+                    (Some(external_id), _) if !seen_external_ids.insert(external_id.clone()) => ImportRowStatus::Duplicate,
+                    (_, Some(count)) if *count > 0 => {
+                        *count -= 1;
+                        ImportRowStatus::Duplicate
+                    }
+                    _ => ImportRowStatus::New,
+                };
+                let result = ImportRowResult {
+                    line: row.line,
+                    status,
+                    transaction_date: Some(row.transaction_date),
+                    transaction_amount: Some(row.categorized_amount(category_type)),
+                    transaction_type: Some(category_type),
+                    transaction_description: row.transaction_description.clone(),
+                    message: None,
+                };
+                (result, Some(row))
+            }
+            Err(e) => (invalid_row(e), None),
+        })
+        .collect()
+}
+
+// 明細の行を重複判定し、dry_run でなければ新しい行を 1 つの取り込みとして登録する
+pub async fn run_import(
+    db_pool: &PgPool,
+    account_id: i32,
+    mapping_id: Option<i32>,
+    source: &str,
+    child_category_id: i32,
+    dry_run: bool,
+    rows: Vec<ParsedRow>,
+) -> Result<ImportResult, ApiError> {
+    let category_type = fetch_category_type(db_pool, child_category_id).await?;
+    let mut classified = classify(db_pool, account_id, category_type, rows).await?;
+
+    let has_new_rows = classified.iter().any(|(result, _)| result.status == ImportRowStatus::New);
+    let import_batch_id = if dry_run || !has_new_rows {
+        None
+    } else {
+        let mut tx = db_pool.begin().await?;
+
+        let import_batch_id = query_scalar!(
+            "INSERT INTO ImportBatches (account_id, mapping_id, source, row_count) VALUES ($1, $2, $3, 0) RETURNING import_batch_id",
+            account_id,
+            mapping_id,
+            source
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut transaction_ids = Vec::new();
+        for (result, row) in classified.iter_mut() {
+            let row = match row {
+                Some(row) if result.status == ImportRowStatus::New => row,
+                _ => continue,
+            };
+            let transaction_amount = row.categorized_amount(category_type);
+            // 同時に取り込まれた場合も取引 ID の一意制約で二重に登録しない
+            let transaction_id = query_scalar!(
+                "INSERT INTO Transactions (account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description, import_batch_id, external_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (account_id, external_id) WHERE external_id IS NOT NULL DO NOTHING
+                RETURNING transaction_id",
+                account_id,
+                child_category_id,
+                transaction_amount.as_decimal(),
+                category_type as i32,
+                row.transaction_date,
+                row.transaction_description,
+                import_batch_id,
+                row.external_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            match transaction_id {
+                Some(transaction_id) => transaction_ids.push(transaction_id),
+                None => result.status = ImportRowStatus::Duplicate,
+            }
+        }
+
+        // 登録した行がなければ取り込みも残さない
+        if transaction_ids.is_empty() {
+            tx.rollback().await?;
+            None
+        } else {
+            query!(
+                "UPDATE ImportBatches SET row_count = $1 WHERE import_batch_id = $2",
+                transaction_ids.len() as i32,
+                import_batch_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            record_budget_alerts(&mut *tx, &transaction_ids).await?;
+
+            tx.commit().await?;
+
+            Some(import_batch_id)
+        }
+    };
+
+    // 件数は実際に登録した結果で数える
+    let count = |status| classified.iter().filter(|(result, _)| result.status == status).count();
+    let imported_count = count(ImportRowStatus::New);
+    let duplicate_count = count(ImportRowStatus::Duplicate);
+    let invalid_count = count(ImportRowStatus::Invalid);
+
+    Ok(ImportResult {
+        import_batch_id,
+        dry_run,
+        imported_count,
+        duplicate_count,
+        invalid_count,
+        rows: classified.into_iter().map(|(result, _)| result).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn money(value: &str) -> Money {
+        Money::from_str(value).unwrap()
+    }
+
+    fn row(line: u64, amount: &str, transaction_type: TransactionType, description: &str, external_id: Option<&str>) -> ParsedRow {
+        Ok(StatementRow {
+            line,
+            transaction_date: date("2024-04-01"),
+            transaction_amount: money(amount),
+            transaction_type,
+            transaction_description: Some(description.to_string()),
+            external_id: external_id.map(str::to_string),
+        })
+    }
+
+    fn existing(amount: &str, transaction_type: TransactionType, description: &str, external_id: Option<&str>) -> ExistingTransaction {
+        ExistingTransaction {
+            transaction_date: date("2024-04-01"),
+            transaction_amount: money(amount),
+            transaction_type,
+            transaction_description: Some(description.to_string()),
+            external_id: external_id.map(str::to_string),
+        }
+    }
+
+    fn statuses(rows: Vec<ParsedRow>, existing: Vec<ExistingTransaction>, seen_external_ids: &[&str]) -> Vec<ImportRowStatus> {
+        let seen_external_ids = seen_external_ids.iter().map(|id| id.to_string()).collect();
+        classify_rows(TransactionType::Expense, rows, existing, seen_external_ids)
+            .into_iter()
+            .map(|(result, _)| result.status)
+            .collect()
+    }
+
+    #[test]
+    fn line_counter_counts_lines_up_to_offset() {
+        let data = "a\nbb\n\nccc";
+        let mut counter = LineCounter::new(data);
+        assert_eq!(counter.line_at(0), 1);
+        assert_eq!(counter.line_at(2), 2);
+        assert_eq!(counter.line_at(6), 4);
+        assert_eq!(counter.line_at(data.len() + 10), 4);
+    }
+
+    #[test]
+    fn line_counter_restarts_when_offset_moves_backwards() {
+        let mut counter = LineCounter::new("a\nb\nc");
+        assert_eq!(counter.line_at(4), 3);
+        assert_eq!(counter.line_at(2), 2);
+    }
+
+    #[test]
+    fn fingerprint_ignores_whitespace_and_case_in_description() {
+        let amount = money("1200");
+        assert_eq!(
+            fingerprint(date("2024-04-01"), &amount, TransactionType::Expense, Some("  Coffee   SHOP ")),
+            fingerprint(date("2024-04-01"), &amount, TransactionType::Expense, Some("coffee shop")),
+        );
+        assert_eq!(
+            fingerprint(date("2024-04-01"), &amount, TransactionType::Expense, None),
+            fingerprint(date("2024-04-01"), &amount, TransactionType::Expense, Some("")),
+        );
+    }
+
+    #[test]
+    fn fingerprint_distinguishes_date_amount_and_type() {
+        let key = fingerprint(date("2024-04-01"), &money("1200"), TransactionType::Expense, Some("coffee"));
+        assert_ne!(key, fingerprint(date("2024-04-02"), &money("1200"), TransactionType::Expense, Some("coffee")));
+        assert_ne!(key, fingerprint(date("2024-04-01"), &money("1201"), TransactionType::Expense, Some("coffee")));
+        assert_ne!(key, fingerprint(date("2024-04-01"), &money("1200"), TransactionType::Income, Some("coffee")));
+    }
+
+    #[test]
+    fn split_signed_amount_follows_amount_sign() {
+        let (amount, transaction_type) = split_signed_amount(money("-500"), AmountSign::NegativeExpense).unwrap();
+        assert_eq!((amount, transaction_type), (money("500"), TransactionType::Expense));
+        let (amount, transaction_type) = split_signed_amount(money("500"), AmountSign::NegativeExpense).unwrap();
+        assert_eq!((amount, transaction_type), (money("500"), TransactionType::Income));
+        let (amount, transaction_type) = split_signed_amount(money("500"), AmountSign::PositiveExpense).unwrap();
+        assert_eq!((amount, transaction_type), (money("500"), TransactionType::Expense));
+        let (amount, transaction_type) = split_signed_amount(money("-500"), AmountSign::PositiveExpense).unwrap();
+        assert_eq!((amount, transaction_type), (money("500"), TransactionType::Income));
+    }
+
+    #[test]
+    fn split_signed_amount_rejects_zero() {
+        assert!(split_signed_amount(Money::zero(), AmountSign::NegativeExpense).is_err());
+    }
+
+    #[test]
+    fn statement_amount_flips_negative_transactions() {
+        assert_eq!(
+            statement_amount(TransactionType::Expense, money("-300")),
+            (money("300"), TransactionType::Income)
+        );
+        assert_eq!(
+            statement_amount(TransactionType::Expense, money("300")),
+            (money("300"), TransactionType::Expense)
+        );
+    }
+
+    #[test]
+    fn classify_rows_marks_only_rows_beyond_existing_count_as_new() {
+        let rows = vec![
+            row(2, "800", TransactionType::Expense, "lunch", None),
+            row(3, "800", TransactionType::Expense, "Lunch", None),
+            row(4, "900", TransactionType::Expense, "lunch", None),
+        ];
+        let existing = vec![existing("800", TransactionType::Expense, "lunch", None)];
+        assert_eq!(
+            statuses(rows, existing, &[]),
+            vec![ImportRowStatus::Duplicate, ImportRowStatus::New, ImportRowStatus::New]
+        );
+    }
+
+    #[test]
+    fn classify_rows_matches_negative_transactions_against_opposite_rows() {
+        let rows = vec![row(2, "300", TransactionType::Income, "refund", None)];
+        let existing = vec![existing("-300", TransactionType::Expense, "refund", None)];
+        assert_eq!(statuses(rows, existing, &[]), vec![ImportRowStatus::Duplicate]);
+    }
+
+    #[test]
+    fn classify_rows_uses_external_ids() {
+        let rows = vec![
+            row(2, "800", TransactionType::Expense, "lunch", Some("A1")),
+            row(3, "800", TransactionType::Expense, "lunch", Some("B1")),
+            row(4, "800", TransactionType::Expense, "lunch", Some("B1")),
+        ];
+        // 取引 ID の異なる既存の取引とは内容が同じでも照合しない
+        let existing = vec![existing("800", TransactionType::Expense, "lunch", Some("A1"))];
+        assert_eq!(
+            statuses(rows, existing, &["A1"]),
+            vec![ImportRowStatus::Duplicate, ImportRowStatus::New, ImportRowStatus::Duplicate]
+        );
+    }
+
+    #[test]
+    fn classify_rows_matches_rows_with_external_id_against_unidentified_transactions() {
+        let rows = vec![
+            row(2, "800", TransactionType::Expense, "lunch", Some("A1")),
+            row(3, "800", TransactionType::Expense, "lunch", Some("A2")),
+        ];
+        let existing = vec![existing("800", TransactionType::Expense, "lunch", None)];
+        assert_eq!(
+            statuses(rows, existing, &[]),
+            vec![ImportRowStatus::Duplicate, ImportRowStatus::New]
+        );
+    }
+
+    #[test]
+    fn classify_rows_keeps_invalid_rows() {
+        let rows = vec![Err(RowError { line: 5, message: "bad".to_string() })];
+        let classified = classify_rows(TransactionType::Expense, rows, Vec::new(), HashSet::new());
+        assert_eq!(classified[0].0.status, ImportRowStatus::Invalid);
+        assert_eq!(classified[0].0.line, 5);
+        assert!(classified[0].1.is_none());
+    }
+
+    #[test]
+    fn classify_rows_registers_rows_in_category_type() {
+        let rows = vec![row(2, "300", TransactionType::Income, "refund", None)];
+        let classified = classify_rows(TransactionType::Expense, rows, Vec::new(), HashSet::new());
+        assert_eq!(classified[0].0.transaction_type, Some(TransactionType::Expense));
+        assert_eq!(classified[0].0.transaction_amount, Some(money("-300")));
+    }
+}
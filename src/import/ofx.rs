@@ -0,0 +1,210 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::error::ApiError;
+use crate::models::import::AmountSign;
+use crate::models::money::Money;
+use super::{split_signed_amount, LineCounter, ParsedRow, RowError, StatementRow};
+
+// OFX 1.x (SGML) は値を持つ要素に終了タグがないため、XML として読まずに
+// タグとその直後の値を順に取り出す。OFX 2.x (XML) も同じ方法で読める
+struct Element<'a> {
+    offset: usize,
+    tag: String,
+    value: &'a str,
+}
+
+fn elements(data: &str) -> Vec<Element<'_>> {
+    let mut elements = Vec::new();
+    let mut rest = data;
+    let mut offset = 0;
+
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_ascii_uppercase();
+        let after = &rest[start + end + 1..];
+        let value_end = after.find('<').unwrap_or(after.len());
+
+        elements.push(Element { offset: offset + start, tag, value: after[..value_end].trim() });
+
+        offset += start + end + 1;
+        rest = after;
+    }
+
+    elements
+}
+
+// YYYYMMDD[HHMMSS[.XXX]][[gmt offset:tz name]] の日付部分
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_transaction(fields: &HashMap<String, &str>, line: u64) -> ParsedRow {
+    let row_error = |message: String| RowError { line, message };
+    let field = |name: &str| fields.get(name).copied().filter(|value| !value.is_empty());
+
+    let date_value = field("DTPOSTED").ok_or_else(|| row_error("DTPOSTED is missing".to_string()))?;
+    let transaction_date = parse_date(date_value)
+        .ok_or_else(|| row_error(format!("\"{}\" is not a valid DTPOSTED", date_value)))?;
+
+    let amount_value = field("TRNAMT").ok_or_else(|| row_error("TRNAMT is missing".to_string()))?;
+    // 小数点にカンマを使う銀行がある
+    let amount = Money::from_str(&amount_value.replace(',', "."))
+        .map_err(|e| row_error(format!("\"{}\" is not a valid TRNAMT: {}", amount_value, e)))?;
+    let (transaction_amount, transaction_type) = split_signed_amount(amount, AmountSign::NegativeExpense).map_err(row_error)?;
+
+    let transaction_description = field("NAME").or_else(|| field("MEMO")).map(xml_unescape);
+    let external_id = field("FITID").map(xml_unescape);
+
+    Ok(StatementRow { line, transaction_date, transaction_amount, transaction_type, transaction_description, external_id })
+}
+
+// 口座明細 (STMTTRN) を取り出す。金額は出金が負の値
+pub fn parse(data: &str) -> Result<Vec<ParsedRow>, ApiError> {
+    let elements = elements(data);
+
+    if !elements.iter().any(|element| element.tag == "OFX") {
+        return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_ofx", "the file is not an OFX document"));
+    }
+
+    let mut lines = LineCounter::new(data);
+
+    let mut rows = Vec::new();
+    let mut current: Option<(u64, HashMap<String, &str>)> = None;
+    for element in &elements {
+        match element.tag.as_str() {
+            "STMTTRN" => current = Some((lines.line_at(element.offset), HashMap::new())),
+            "/STMTTRN" => {
+                if let Some((line, fields)) = current.take() {
+                    rows.push(parse_transaction(&fields, line));
+                }
+            }
+            tag if !tag.starts_with('/') => {
+                if let Some((_, fields)) = current.as_mut() {
+                    fields.insert(element.tag.clone(), element.value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transaction::TransactionType;
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1>
+<STMTTRNRS>
+<STMTRS>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20240401120000.000[-5:EST]
+<TRNAMT>-12,50
+<FITID>T1
+<NAME>Coffee &amp; Co
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20240425
+<TRNAMT>2500.00
+<FITID>T2
+<MEMO>Salary
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS>
+</STMTTRNRS>
+</BANKMSGSRSV1>
+</OFX>
+";
+
+    fn money(value: &str) -> Money {
+        Money::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn parse_reads_sgml_transactions() {
+        let rows = parse(SGML).unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let first = rows[0].as_ref().ok().unwrap();
+        assert_eq!(first.line, 10);
+        assert_eq!(first.transaction_date, NaiveDate::from_ymd_opt(2024, 4, 1).unwrap());
+        assert_eq!(first.transaction_amount, money("12.50"));
+        assert_eq!(first.transaction_type, TransactionType::Expense);
+        assert_eq!(first.transaction_description.as_deref(), Some("Coffee & Co"));
+        assert_eq!(first.external_id.as_deref(), Some("T1"));
+
+        let second = rows[1].as_ref().ok().unwrap();
+        assert_eq!(second.line, 17);
+        assert_eq!(second.transaction_amount, money("2500"));
+        assert_eq!(second.transaction_type, TransactionType::Income);
+        assert_eq!(second.transaction_description.as_deref(), Some("Salary"));
+    }
+
+    #[test]
+    fn parse_reads_xml_transactions() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST>
+<STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20240402</DTPOSTED><TRNAMT>-8.00</TRNAMT><FITID>X1</FITID><NAME>Books</NAME></STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>"#;
+        let rows = parse(data).unwrap();
+        assert_eq!(rows.len(), 1);
+
+        let row = rows[0].as_ref().ok().unwrap();
+        assert_eq!(row.line, 4);
+        assert_eq!(row.transaction_amount, money("8"));
+        assert_eq!(row.transaction_type, TransactionType::Expense);
+        assert_eq!(row.transaction_description.as_deref(), Some("Books"));
+        assert_eq!(row.external_id.as_deref(), Some("X1"));
+    }
+
+    #[test]
+    fn parse_reports_invalid_transactions() {
+        let data = "<OFX>
+<STMTTRN>
+<DTPOSTED>20240401
+<FITID>A
+</STMTTRN>
+<STMTTRN>
+<DTPOSTED>2024-04-01
+<TRNAMT>-1.00
+</STMTTRN>
+<STMTTRN>
+<DTPOSTED>20240401
+<TRNAMT>0
+</STMTTRN>
+</OFX>";
+        let errors: Vec<_> = parse(data).unwrap().into_iter().map(|row| row.err().unwrap()).collect();
+        assert_eq!(errors.len(), 3);
+        assert_eq!((errors[0].line, errors[0].message.as_str()), (2, "TRNAMT is missing"));
+        assert_eq!((errors[1].line, errors[1].message.as_str()), (6, "\"2024-04-01\" is not a valid DTPOSTED"));
+        assert_eq!((errors[2].line, errors[2].message.as_str()), (10, "the amount must not be zero"));
+    }
+
+    #[test]
+    fn parse_rejects_documents_without_ofx_element() {
+        assert!(parse("<html><body>statement</body></html>").is_err());
+    }
+}
@@ -1,5 +1,14 @@
+pub mod auth;
+pub mod budget_templates;
 pub mod db;
+pub mod error;
+pub mod exchange_rates;
 pub mod handlers;
+pub mod import;
+pub mod migrate;
 pub mod models;
+pub mod notifications;
+pub mod recurring;
 pub mod routes;
 pub mod serializers;
+pub mod validation;
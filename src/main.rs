@@ -1,18 +1,17 @@
-use axum;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
 use tracing_subscriber::EnvFilter;
 
-mod db;
-mod handlers;
-mod models;
-mod routes;
-mod serializers;
+use clynelish_backend::{auth, budget_templates, db, exchange_rates, migrate, notifications, recurring, routes};
+use clynelish_backend::auth::token::AuthConfig;
+
+const USAGE: &str = "usage: clynelish-backend [serve | migrate [run | status | revert | hash-passwords]]";
 
 #[tokio::main]
 async fn main() {
-    println!("Starting server...");
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
     let env_filter = EnvFilter::from_default_env();
 
@@ -27,7 +26,68 @@ async fn main() {
         .await
         .expect("Failed to create pool.");
 
-    let state = Arc::new(Mutex::new(db::AppState { db_pool }));
+    match args.first().map(String::as_str) {
+        None | Some("serve") => serve(db_pool).await,
+        Some("migrate") => run_migrate_command(&db_pool, args.get(1).map(String::as_str)).await,
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+async fn run_migrate_command(db_pool: &PgPool, subcommand: Option<&str>) {
+    match subcommand {
+        None | Some("run") => {
+            migrate::run(db_pool).await.expect("Failed to run migrations.");
+            println!("Migrations applied.");
+        }
+        Some("status") => {
+            let migrations = migrate::status(db_pool).await.expect("Failed to read migration status.");
+            for migration in migrations {
+                let state = if migration.applied { "applied" } else { "pending" };
+                println!("{:>4} {:<8} {}", migration.version, state, migration.description);
+            }
+        }
+        Some("revert") => {
+            match migrate::revert_last(db_pool).await.expect("Failed to revert migration.") {
+                Some(version) => println!("Reverted migration {}.", version),
+                None => println!("No migrations to revert."),
+            }
+        }
+        // 平文のパスワードの変換は全件を走査するため、起動時ではなく移行時に一度だけ実行する
+        Some("hash-passwords") => {
+            let hashed = auth::password::hash_plaintext_passwords(db_pool)
+                .await
+                .expect("Failed to hash plaintext passwords.");
+            println!("Hashed {} plaintext passwords.", hashed);
+        }
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+async fn serve(db_pool: PgPool) {
+    println!("Starting server...");
+
+    let auth = AuthConfig::from_env();
+
+    migrate::run(&db_pool).await.expect("Failed to run migrations.");
+
+    if let Ok(path) = std::env::var("EXCHANGE_RATES_FILE") {
+        let loaded = exchange_rates::load_file(&db_pool, &path)
+            .await
+            .expect("Failed to load exchange rates.");
+        tracing::info!("Loaded {} exchange rates from {}", loaded, path);
+    }
+
+    recurring::spawn_poster(db_pool.clone(), recurring::post_interval_from_env());
+    budget_templates::spawn_generator(db_pool.clone(), budget_templates::generate_interval_from_env());
+    notifications::spawn_dispatcher(db_pool.clone(), notifications::delivery_from_env(), notifications::delivery_interval_from_env());
+
+    let state = Arc::new(Mutex::new(db::AppState { db_pool, auth }));
 
     let app = routes::create_routes(state);
 
@@ -36,4 +96,4 @@ async fn main() {
         .await
         .unwrap();
     axum::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+}
@@ -0,0 +1,57 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+// migrations/ 以下の SQL をバイナリに埋め込む
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+pub async fn run(db_pool: &PgPool) -> Result<(), MigrateError> {
+    MIGRATOR.run(db_pool).await
+}
+
+async fn applied_versions(db_pool: &PgPool) -> Result<Vec<i64>, MigrateError> {
+    let mut conn = db_pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    let mut versions: Vec<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+pub async fn status(db_pool: &PgPool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let applied: HashSet<i64> = applied_versions(db_pool).await?.into_iter().collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied: applied.contains(&migration.version),
+        })
+        .collect())
+}
+
+// 最後に適用したマイグレーションを 1 つだけ取り消し、そのバージョンを返す
+pub async fn revert_last(db_pool: &PgPool) -> Result<Option<i64>, MigrateError> {
+    let applied = applied_versions(db_pool).await?;
+    let last = match applied.last() {
+        Some(&last) => last,
+        None => return Ok(None),
+    };
+    let target = applied.iter().rev().nth(1).copied().unwrap_or(0);
+
+    MIGRATOR.undo(db_pool, target).await?;
+    Ok(Some(last))
+}
@@ -1,14 +1,72 @@
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDateTime;
-use sqlx::types::BigDecimal;
-use crate::serializers::bigdecimal_serde;
+use chrono::{NaiveDate, NaiveDateTime};
+use validator::{Validate, ValidationError};
+use crate::models::period::Granularity;
+use crate::models::money::{InCurrency, Money};
+use crate::validation::{schema_error, validate_currency_code, validate_money};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Validate)]
 pub struct Account {
     pub account_id: Option<i32>,
+    // 作成時は認証済みユーザーの ID で上書きされる
+    #[serde(default)]
     pub user_id: i32,
+    #[validate(length(min = 1, max = 50))]
     pub account_name: String,
-    #[serde(with = "bigdecimal_serde")]
-    pub initial_balance: BigDecimal,
+    #[validate(custom(function = "validate_money"))]
+    pub initial_balance: Money,
+    // 作成時に省略するとユーザーの base_currency、更新時に省略すると変更しない。取引のある口座は変更できない
+    #[validate(custom(function = "validate_currency_code"))]
+    pub currency: Option<String>,
     pub created_at: Option<NaiveDateTime>,
+    // 初期残高に取引を反映した残高 (レスポンスのみ)
+    #[serde(default, skip_deserializing)]
+    pub current_balance: Option<Money>,
+}
+
+impl InCurrency for Account {
+    fn in_currency(self, currency: &str) -> Self {
+        Account {
+            initial_balance: self.initial_balance.in_currency(currency),
+            current_balance: self.current_balance.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+// GET /accounts/:id/balance-history のクエリパラメータ
+#[derive(Deserialize, Validate)]
+#[validate(schema(function = "validate_balance_history_range"))]
+pub struct BalanceHistoryQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub granularity: Granularity,
+}
+
+fn validate_balance_history_range(query: &BalanceHistoryQuery) -> Result<(), ValidationError> {
+    if query.to < query.from {
+        return Err(schema_error("to", "date_range", "must not be before from"));
+    }
+    // 日次で 10 年を超える系列は返さない
+    if query.granularity == Granularity::Day && (query.to - query.from).num_days() > 3660 {
+        return Err(schema_error("from", "date_range", "daily history is limited to 10 years"));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct BalancePoint {
+    pub period_start: NaiveDate,
+    pub net_change: Money,
+    pub balance: Money,
+}
+
+impl InCurrency for BalancePoint {
+    fn in_currency(self, currency: &str) -> Self {
+        BalancePoint {
+            net_change: self.net_change.in_currency(currency),
+            balance: self.balance.in_currency(currency),
+            ..self
+        }
+    }
 }
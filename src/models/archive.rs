@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveDateTime};
+use validator::Validate;
+use crate::models::account::Account;
+use crate::models::budget::Budget;
+use crate::models::child_category::ChildCategory;
+use crate::models::parent_category::ParentCategory;
+use crate::models::transaction::Transaction;
+use crate::models::transfer::Transfer;
+
+// 2: 取引の種別をカテゴリの種別に揃え、逆向きのお金の動きを負の金額で記録する
+pub const ARCHIVE_FORMAT_VERSION: i32 = 2;
+
+// ユーザーの家計簿全体。ID はエクスポート元のもので、インポート時に振り直される。
+// 振替の取引は transfers から作り直すため transactions には含めない
+#[derive(Deserialize, Serialize, Validate)]
+pub struct LedgerArchive {
+    #[validate(range(min = 1, max = 2))]
+    pub format_version: i32,
+    #[serde(default)]
+    pub exported_at: Option<NaiveDateTime>,
+    #[validate(nested)]
+    pub accounts: Vec<Account>,
+    #[validate(nested)]
+    pub parent_categories: Vec<ParentCategory>,
+    #[validate(nested)]
+    pub child_categories: Vec<ChildCategory>,
+    #[validate(nested)]
+    pub transactions: Vec<Transaction>,
+    #[validate(nested)]
+    pub transfers: Vec<Transfer>,
+    #[validate(nested)]
+    pub budgets: Vec<Budget>,
+}
+
+// インポートで作成した件数
+#[derive(Serialize, Default)]
+pub struct ArchiveImportSummary {
+    pub accounts: usize,
+    pub parent_categories: usize,
+    pub child_categories: usize,
+    pub transactions: usize,
+    pub transfers: usize,
+    pub budgets: usize,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    // UTF-8 の BOM と CRLF 改行付きの CSV。Excel でそのまま開ける
+    Excel,
+    Json,
+}
+
+fn default_export_format() -> ExportFormat {
+    ExportFormat::Csv
+}
+
+// GET /users/:id/export のクエリパラメータ。期間は CSV にのみ適用する
+#[derive(Deserialize, Validate)]
+pub struct ExportQuery {
+    #[serde(default = "default_export_format")]
+    pub format: ExportFormat,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
@@ -1,15 +1,155 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
 use sqlx::types::BigDecimal;
+use validator::{Validate, ValidationError};
+use crate::models::money::{InCurrency, Money};
+use crate::models::period::Month;
 use crate::serializers::bigdecimal_serde;
+use crate::validation::{schema_error, validate_alert_thresholds, validate_money, validate_positive_amount};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_budget_period", skip_on_field_errors = false))]
 pub struct Budget {
     pub budget_id: Option<i32>,
+    // 作成時は認証済みユーザーの ID で上書きされる
+    #[serde(default)]
     pub user_id: i32,
     pub child_category_id: i32,
-    #[serde(with = "bigdecimal_serde")]
-    pub amount: BigDecimal,
+    #[validate(custom(function = "validate_positive_amount"))]
+    pub amount: Money,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
+    // 前の期間から繰り越した金額。負の値は超過分の持ち越しで、amount との合計が使える金額になる
+    #[serde(default)]
+    #[validate(custom(function = "validate_money"))]
+    pub rollover_amount: Money,
+    // テンプレートから作成された予算の場合のテンプレート ID (レスポンスのみ)
+    #[serde(default, skip_deserializing)]
+    pub template_id: Option<i32>,
+    // 消化率 (%) がこれらの値に達したときに通知する
+    #[serde(default = "default_alert_thresholds")]
+    #[validate(custom(function = "validate_alert_thresholds"))]
+    pub alert_thresholds: Vec<i32>,
+    // 以下は消化状況 (レスポンスのみ)。spent は期間中の支出の合計、percent_used は繰越額を含めた金額に対する割合 (%)、
+    // projected_spending は現在のペースで期末まで支出した場合の見込み
+    #[serde(default, skip_deserializing)]
+    pub spent: Option<Money>,
+    #[serde(default, skip_deserializing)]
+    pub remaining: Option<Money>,
+    #[serde(default, skip_deserializing, with = "bigdecimal_serde::option")]
+    pub percent_used: Option<BigDecimal>,
+    #[serde(default, skip_deserializing)]
+    pub projected_spending: Option<Money>,
+}
+
+impl InCurrency for Budget {
+    fn in_currency(self, currency: &str) -> Self {
+        Budget {
+            amount: self.amount.in_currency(currency),
+            rollover_amount: self.rollover_amount.in_currency(currency),
+            spent: self.spent.in_currency(currency),
+            remaining: self.remaining.in_currency(currency),
+            projected_spending: self.projected_spending.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+fn default_alert_thresholds() -> Vec<i32> {
+    vec![80, 100]
+}
+
+fn validate_budget_period(budget: &Budget) -> Result<(), ValidationError> {
+    if budget.end_date >= budget.start_date {
+        Ok(())
+    } else {
+        Err(schema_error("end_date", "date_range", "must not be before start_date"))
+    }
+}
+
+// GET /users/:id/budgets のクエリパラメータ。期間を省略した場合は今月
+#[derive(Deserialize, Validate)]
+pub struct BudgetOverviewQuery {
+    pub period: Option<Month>,
+}
+
+// 親カテゴリごとの予算のまとめ
+#[derive(Serialize)]
+pub struct BudgetGroup {
+    pub parent_category_id: i32,
+    pub parent_category_name: String,
+    pub budgeted: Money,
+    pub spent: Money,
+    pub remaining: Money,
+    pub budgets: Vec<Budget>,
+}
+
+impl InCurrency for BudgetGroup {
+    fn in_currency(self, currency: &str) -> Self {
+        BudgetGroup {
+            budgeted: self.budgeted.in_currency(currency),
+            spent: self.spent.in_currency(currency),
+            remaining: self.remaining.in_currency(currency),
+            budgets: self.budgets.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+// 期間中に支出があるが予算のない子カテゴリ
+#[derive(Serialize)]
+pub struct UnbudgetedCategory {
+    pub child_category_id: i32,
+    pub child_category_name: String,
+    pub parent_category_id: i32,
+    pub parent_category_name: String,
+    pub spent: Money,
+}
+
+impl InCurrency for UnbudgetedCategory {
+    fn in_currency(self, currency: &str) -> Self {
+        UnbudgetedCategory {
+            spent: self.spent.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UnbudgetedSpending {
+    pub spent: Money,
+    pub categories: Vec<UnbudgetedCategory>,
+}
+
+impl InCurrency for UnbudgetedSpending {
+    fn in_currency(self, currency: &str) -> Self {
+        UnbudgetedSpending {
+            spent: self.spent.in_currency(currency),
+            categories: self.categories.in_currency(currency),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BudgetOverview {
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub budgeted: Money,
+    pub spent: Money,
+    pub remaining: Money,
+    pub groups: Vec<BudgetGroup>,
+    pub unbudgeted: UnbudgetedSpending,
+}
+
+impl InCurrency for BudgetOverview {
+    fn in_currency(self, currency: &str) -> Self {
+        BudgetOverview {
+            budgeted: self.budgeted.in_currency(currency),
+            spent: self.spent.in_currency(currency),
+            remaining: self.remaining.in_currency(currency),
+            groups: self.groups.in_currency(currency),
+            unbudgeted: self.unbudgeted.in_currency(currency),
+            ..self
+        }
+    }
 }
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use chrono::{Days, Months, NaiveDate};
+use validator::{Validate, ValidationError};
+use crate::models::money::{InCurrency, Money};
+use crate::validation::{schema_error, validate_positive_amount};
+
+#[derive(Deserialize, Serialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// 前の期間の残額を次の期間に繰り越す方法
+#[derive(Deserialize, Serialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum Rollover {
+    #[default]
+    None,
+    // 使い残した分だけを繰り越す
+    Unspent,
+    // 使いすぎた分だけを次の期間から差し引く
+    Overspent,
+    Both,
+}
+
+impl Rollover {
+    // 前の期間の残額 (超過した場合は負の値) のうち、次の期間に繰り越す金額
+    pub fn carry(&self, remaining: Money) -> Money {
+        let zero = Money::zero();
+        match self {
+            Rollover::None => zero,
+            Rollover::Unspent => remaining.max(zero),
+            Rollover::Overspent => remaining.min(zero),
+            Rollover::Both => remaining,
+        }
+    }
+}
+
+fn default_interval_count() -> i32 {
+    1
+}
+
+// 期間ごとに予算を作成するテンプレート。期間は anchor_date から period × interval_count ずつ区切る。
+// 給料日の 25 日から始まる月次予算なら anchor_date に 25 日を指定する
+#[derive(Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_template", skip_on_field_errors = false))]
+pub struct BudgetTemplate {
+    pub template_id: Option<i32>,
+    // 作成時は認証済みユーザーの ID で上書きされる
+    #[serde(default)]
+    pub user_id: i32,
+    pub child_category_id: i32,
+    #[validate(custom(function = "validate_positive_amount"))]
+    pub amount: Money,
+    pub period: BudgetPeriod,
+    #[serde(default = "default_interval_count")]
+    #[validate(range(min = 1, max = 366))]
+    pub interval_count: i32,
+    pub anchor_date: NaiveDate,
+    // この日より後に始まる期間の予算は作成しない
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub rollover: Rollover,
+    #[serde(default, skip_deserializing)]
+    pub generated_through: Option<NaiveDate>,
+}
+
+impl InCurrency for BudgetTemplate {
+    fn in_currency(self, currency: &str) -> Self {
+        BudgetTemplate {
+            amount: self.amount.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+fn validate_template(template: &BudgetTemplate) -> Result<(), ValidationError> {
+    match template.end_date {
+        Some(end_date) if end_date < template.anchor_date => {
+            Err(schema_error("end_date", "date_range", "must not be before anchor_date"))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl BudgetTemplate {
+    // n 番目 (0 始まり) の期間の開始日。月末の anchor_date は短い月では月末に丸める
+    fn period_start(&self, n: u32) -> Option<NaiveDate> {
+        let step = n.checked_mul(self.interval_count as u32)?;
+        match self.period {
+            BudgetPeriod::Weekly => self.anchor_date.checked_add_days(Days::new(step as u64 * 7)),
+            BudgetPeriod::Monthly => self.anchor_date.checked_add_months(Months::new(step)),
+            BudgetPeriod::Yearly => self.anchor_date.checked_add_months(Months::new(step.checked_mul(12)?)),
+        }
+    }
+
+    // 期間 (開始日, 終了日) を古い順に返す
+    pub fn periods(&self) -> impl Iterator<Item = (NaiveDate, NaiveDate)> + '_ {
+        (0..)
+            .map_while(|n| Some((self.period_start(n)?, self.period_start(n + 1)?.pred_opt()?)))
+            .take_while(|(start_date, _)| self.end_date.is_none_or(|end_date| *start_date <= end_date))
+    }
+
+    // まだ予算を作成していない期間
+    pub fn pending_periods(&self) -> impl Iterator<Item = (NaiveDate, NaiveDate)> + '_ {
+        self.periods()
+            .skip_while(|(start_date, _)| self.generated_through.is_some_and(|generated| *start_date <= generated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn money(value: &str) -> Money {
+        Money::from_str(value).unwrap()
+    }
+
+    fn template(period: BudgetPeriod, interval_count: i32, anchor_date: &str) -> BudgetTemplate {
+        BudgetTemplate {
+            template_id: Some(1),
+            user_id: 1,
+            child_category_id: 1,
+            amount: money("30000"),
+            period,
+            interval_count,
+            anchor_date: date(anchor_date),
+            end_date: None,
+            rollover: Rollover::None,
+            generated_through: None,
+        }
+    }
+
+    fn periods(template: &BudgetTemplate, limit: usize) -> Vec<(NaiveDate, NaiveDate)> {
+        template.pending_periods().take(limit).collect()
+    }
+
+    #[test]
+    fn monthly_periods_start_on_anchor_day() {
+        let template = template(BudgetPeriod::Monthly, 1, "2024-01-25");
+        assert_eq!(
+            periods(&template, 3),
+            vec![
+                (date("2024-01-25"), date("2024-02-24")),
+                (date("2024-02-25"), date("2024-03-24")),
+                (date("2024-03-25"), date("2024-04-24")),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_periods_clamp_month_end_anchor() {
+        let template = template(BudgetPeriod::Monthly, 1, "2024-01-31");
+        assert_eq!(
+            periods(&template, 3),
+            vec![
+                (date("2024-01-31"), date("2024-02-28")),
+                (date("2024-02-29"), date("2024-03-30")),
+                (date("2024-03-31"), date("2024-04-29")),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_and_yearly_periods_use_interval_count() {
+        let weekly = template(BudgetPeriod::Weekly, 2, "2024-04-01");
+        assert_eq!(
+            periods(&weekly, 2),
+            vec![(date("2024-04-01"), date("2024-04-14")), (date("2024-04-15"), date("2024-04-28"))]
+        );
+
+        let yearly = template(BudgetPeriod::Yearly, 1, "2024-04-01");
+        assert_eq!(
+            periods(&yearly, 2),
+            vec![(date("2024-04-01"), date("2025-03-31")), (date("2025-04-01"), date("2026-03-31"))]
+        );
+    }
+
+    #[test]
+    fn periods_stop_after_end_date() {
+        let mut template = template(BudgetPeriod::Monthly, 1, "2024-01-01");
+        template.end_date = Some(date("2024-03-01"));
+        assert_eq!(
+            periods(&template, 10),
+            vec![
+                (date("2024-01-01"), date("2024-01-31")),
+                (date("2024-02-01"), date("2024-02-29")),
+                (date("2024-03-01"), date("2024-03-31")),
+            ]
+        );
+    }
+
+    #[test]
+    fn pending_periods_skip_generated_periods() {
+        let mut template = template(BudgetPeriod::Monthly, 1, "2024-01-01");
+        template.generated_through = Some(date("2024-02-01"));
+        assert_eq!(periods(&template, 1), vec![(date("2024-03-01"), date("2024-03-31"))]);
+
+        template.end_date = Some(date("2024-02-15"));
+        assert!(periods(&template, 10).is_empty());
+    }
+
+    #[test]
+    fn rollover_carries_remaining_amount() {
+        assert_eq!(Rollover::None.carry(money("500")), Money::zero());
+        assert_eq!(Rollover::None.carry(money("-500")), Money::zero());
+        assert_eq!(Rollover::Unspent.carry(money("500")), money("500"));
+        assert_eq!(Rollover::Unspent.carry(money("-500")), Money::zero());
+        assert_eq!(Rollover::Overspent.carry(money("500")), Money::zero());
+        assert_eq!(Rollover::Overspent.carry(money("-500")), money("-500"));
+        assert_eq!(Rollover::Both.carry(money("500")), money("500"));
+        assert_eq!(Rollover::Both.carry(money("-500")), money("-500"));
+    }
+}
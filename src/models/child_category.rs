@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Validate)]
 pub struct ChildCategory {
     pub child_category_id: Option<i32>,
     pub parent_category_id: i32,
+    #[validate(length(min = 1, max = 50))]
     pub child_category_name: String,
 }
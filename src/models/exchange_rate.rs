@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use sqlx::types::BigDecimal;
+use validator::{Validate, ValidationError};
+use crate::serializers::bigdecimal_serde;
+use crate::validation::{schema_error, validate_currency_code, validate_exchange_rate};
+
+// 1 base_currency = rate quote_currency。user_id が null のレートは全ユーザー共通
+#[derive(Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_currency_pair", skip_on_field_errors = false))]
+pub struct ExchangeRate {
+    #[serde(default, skip_deserializing)]
+    pub exchange_rate_id: Option<i32>,
+    #[serde(default, skip_deserializing)]
+    pub user_id: Option<i32>,
+    pub rate_date: NaiveDate,
+    #[validate(custom(function = "validate_currency_code"))]
+    pub base_currency: String,
+    #[validate(custom(function = "validate_currency_code"))]
+    pub quote_currency: String,
+    #[serde(with = "bigdecimal_serde")]
+    #[validate(custom(function = "validate_exchange_rate"))]
+    pub rate: BigDecimal,
+}
+
+fn validate_currency_pair(rate: &ExchangeRate) -> Result<(), ValidationError> {
+    if rate.base_currency == rate.quote_currency {
+        Err(schema_error("quote_currency", "same_currency", "must differ from base_currency"))
+    } else {
+        Ok(())
+    }
+}
+
+// POST /exchange-rates のリクエストボディ
+#[derive(Deserialize, Validate)]
+pub struct ExchangeRateUpload {
+    #[validate(length(min = 1, max = 10000), nested)]
+    pub rates: Vec<ExchangeRate>,
+}
+
+#[derive(Serialize)]
+pub struct ExchangeRateUploadResult {
+    pub saved_count: usize,
+}
+
+// GET /exchange-rates のクエリパラメータ
+#[derive(Deserialize, Validate)]
+pub struct ExchangeRateQuery {
+    #[validate(custom(function = "validate_currency_code"))]
+    pub base_currency: Option<String>,
+    #[validate(custom(function = "validate_currency_code"))]
+    pub quote_currency: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
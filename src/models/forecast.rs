@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use sqlx::types::BigDecimal;
+use validator::Validate;
+use crate::models::money::{InCurrency, Money};
+use crate::serializers::bigdecimal_serde;
+use crate::validation::validate_money;
+
+fn default_forecast_months() -> u32 {
+    3
+}
+
+fn default_history_months() -> u32 {
+    6
+}
+
+// GET /accounts/:id/forecast のクエリパラメータ
+#[derive(Deserialize, Validate)]
+pub struct ForecastQuery {
+    // 今日から何か月先まで予測するか
+    #[serde(default = "default_forecast_months")]
+    #[validate(range(min = 3, max = 12))]
+    pub months: u32,
+    // 変動費の平均に使う直近の月数
+    #[serde(default = "default_history_months")]
+    #[validate(range(min = 1, max = 24))]
+    pub history_months: u32,
+    // 残高がこの金額を下回る日を警告する。省略時は 0
+    #[serde(default)]
+    #[validate(custom(function = "validate_money"))]
+    pub low_balance_threshold: Option<Money>,
+}
+
+// 定期取引以外の取引から求めた子カテゴリごとの 1 日あたりの平均。収入は正、支出は負の値
+#[derive(Serialize)]
+pub struct VariableFlow {
+    pub child_category_id: i32,
+    pub child_category_name: String,
+    #[serde(with = "bigdecimal_serde")]
+    pub daily_average: BigDecimal,
+}
+
+// 1 日ごとの予測。recurring は定期取引、variable は変動費の平均による増減
+#[derive(Serialize)]
+pub struct ForecastPoint {
+    pub date: NaiveDate,
+    pub recurring: Money,
+    pub variable: Money,
+    pub balance: Money,
+}
+
+impl InCurrency for ForecastPoint {
+    fn in_currency(self, currency: &str) -> Self {
+        ForecastPoint {
+            recurring: self.recurring.in_currency(currency),
+            variable: self.variable.in_currency(currency),
+            balance: self.balance.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+// 残高がしきい値を下回り続ける期間
+#[derive(Serialize)]
+pub struct LowBalanceWarning {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub lowest_date: NaiveDate,
+    pub lowest_balance: Money,
+}
+
+impl InCurrency for LowBalanceWarning {
+    fn in_currency(self, currency: &str) -> Self {
+        LowBalanceWarning {
+            lowest_balance: self.lowest_balance.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CashFlowForecast {
+    pub account_id: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub starting_balance: Money,
+    pub low_balance_threshold: Money,
+    pub variable_flows: Vec<VariableFlow>,
+    pub series: Vec<ForecastPoint>,
+    pub warnings: Vec<LowBalanceWarning>,
+}
+
+impl InCurrency for CashFlowForecast {
+    fn in_currency(self, currency: &str) -> Self {
+        CashFlowForecast {
+            starting_balance: self.starting_balance.in_currency(currency),
+            low_balance_threshold: self.low_balance_threshold.in_currency(currency),
+            series: self.series.in_currency(currency),
+            warnings: self.warnings.in_currency(currency),
+            ..self
+        }
+    }
+}
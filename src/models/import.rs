@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveDateTime};
+use validator::{Validate, ValidationError};
+use crate::models::money::{InCurrency, Money};
+use crate::models::transaction::TransactionType;
+use crate::validation::schema_error;
+
+// 金額列の符号の意味。明細によって出金を負の値で書くものと正の値で書くものがある
+#[derive(Deserialize, Serialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AmountSign {
+    NegativeExpense,
+    PositiveExpense,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+// CSV の見出し行の列名と取引の項目の対応
+#[derive(Deserialize, Serialize, Validate)]
+pub struct ImportMapping {
+    pub mapping_id: Option<i32>,
+    pub account_id: i32,
+    #[validate(length(min = 1, max = 50))]
+    pub mapping_name: String,
+    #[validate(length(min = 1, max = 100))]
+    pub date_column: String,
+    // chrono の strftime 形式
+    #[serde(default = "default_date_format")]
+    #[validate(length(min = 1, max = 50))]
+    pub date_format: String,
+    #[validate(length(min = 1, max = 100))]
+    pub amount_column: String,
+    pub amount_sign: AmountSign,
+    #[validate(length(min = 1, max = 100))]
+    pub description_column: Option<String>,
+}
+
+// 取り込むファイルの形式。QFX は OFX として読む
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Csv,
+    #[serde(alias = "qfx")]
+    Ofx,
+    Camt053,
+}
+
+impl ImportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "csv",
+            ImportFormat::Ofx => "ofx",
+            ImportFormat::Camt053 => "camt053",
+        }
+    }
+}
+
+fn default_import_format() -> ImportFormat {
+    ImportFormat::Csv
+}
+
+// POST /accounts/:id/imports のクエリパラメータ
+#[derive(Deserialize, Validate)]
+#[validate(schema(function = "validate_import_mapping", skip_on_field_errors = false))]
+pub struct ImportQuery {
+    #[serde(default = "default_import_format")]
+    pub format: ImportFormat,
+    // CSV の場合のみ必要
+    pub mapping_id: Option<i32>,
+    // 取り込んだ取引を割り当てるカテゴリ
+    pub child_category_id: i32,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn validate_import_mapping(query: &ImportQuery) -> Result<(), ValidationError> {
+    match (query.format, query.mapping_id) {
+        (ImportFormat::Csv, None) => Err(schema_error("mapping_id", "required", "is required for CSV imports")),
+        (ImportFormat::Ofx | ImportFormat::Camt053, Some(_)) => {
+            Err(schema_error("mapping_id", "mapping_not_applicable", "can only be used with CSV imports"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ImportBatch {
+    pub import_batch_id: i32,
+    pub account_id: i32,
+    pub mapping_id: Option<i32>,
+    pub source: String,
+    pub row_count: i32,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportRowStatus {
+    New,
+    Duplicate,
+    Invalid,
+}
+
+// 明細 1 行の取り込み結果。line はファイル上の行番号
+#[derive(Serialize)]
+pub struct ImportRowResult {
+    pub line: u64,
+    pub status: ImportRowStatus,
+    pub transaction_date: Option<NaiveDate>,
+    pub transaction_amount: Option<Money>,
+    pub transaction_type: Option<TransactionType>,
+    pub transaction_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl InCurrency for ImportRowResult {
+    fn in_currency(self, currency: &str) -> Self {
+        ImportRowResult {
+            transaction_amount: self.transaction_amount.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    // dry_run の場合と新しい行がない場合は作成されない
+    pub import_batch_id: Option<i32>,
+    pub dry_run: bool,
+    pub imported_count: usize,
+    pub duplicate_count: usize,
+    pub invalid_count: usize,
+    pub rows: Vec<ImportRowResult>,
+}
+
+impl InCurrency for ImportResult {
+    fn in_currency(self, currency: &str) -> Self {
+        ImportResult {
+            rows: self.rows.in_currency(currency),
+            ..self
+        }
+    }
+}
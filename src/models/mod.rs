@@ -1,6 +1,18 @@
+pub mod auth;
 pub mod user;
 pub mod account;
 pub mod parent_category;
 pub mod child_category;
 pub mod transaction;
-pub mod budget;
\ No newline at end of file
+pub mod transfer;
+pub mod budget;
+pub mod period;
+pub mod money;
+pub mod recurring;
+pub mod import;
+pub mod archive;
+pub mod budget_template;
+pub mod notification;
+pub mod report;
+pub mod forecast;
+pub mod exchange_rate;
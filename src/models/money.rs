@@ -0,0 +1,437 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::types::{BigDecimal, Type};
+use sqlx::{Encode, Decode, Postgres, postgres::PgTypeInfo};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{ParseBigDecimalError, Signed, Zero};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub};
+use std::str::FromStr;
+
+// ISO 4217 で補助単位を持たない通貨
+const ZERO_DECIMAL_CURRENCIES: [&str; 17] = [
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND", "VUV", "XAF", "XOF", "XPF",
+];
+
+// ISO 4217 で補助単位が 3 桁の通貨。金額の列は小数 2 桁のため扱わない
+const THREE_DECIMAL_CURRENCIES: [&str; 7] = ["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+// 金額の列で補助単位まで表せる通貨か
+pub fn is_supported_currency(currency: &str) -> bool {
+    !THREE_DECIMAL_CURRENCIES.contains(&currency)
+}
+
+// 通貨の小数点以下の桁数。補助単位が 3 桁の通貨は validate_currency_code で拒否している
+pub fn minor_units(currency: &str) -> i64 {
+    if ZERO_DECIMAL_CURRENCIES.contains(&currency) {
+        0
+    } else {
+        Money::SCALE
+    }
+}
+
+// 小数 scale 桁の金額が金額の列に収まる条件。検証エラーのメッセージで共通に使う
+pub fn amount_limits(scale: i64) -> String {
+    let max = BigDecimal::new(BigInt::from(10).pow(Money::PRECISION) - 1, Money::SCALE).with_scale(scale);
+    if scale == 0 {
+        format!("must be a whole number between -{} and {}", max, max)
+    } else {
+        format!("must have at most {} decimal places and be between -{} and {}", scale, max, max)
+    }
+}
+
+// 全体が precision 桁以下、小数部が scale 桁以下の値か。"1e-10000000" のような指数の大きな値も桁を展開せずに判定する
+pub fn fits_digits(value: &BigDecimal, precision: u32, scale: i64) -> bool {
+    let (digits, exponent) = value.as_bigint_and_exponent();
+    if digits.is_zero() {
+        return true;
+    }
+
+    // 末尾の 0 は小数部の桁数に数えない ("1.50" は小数 1 桁)
+    let text = digits.abs().to_string();
+    let significant = text.trim_end_matches('0');
+    let exponent = exponent.saturating_sub((text.len() - significant.len()) as i64);
+    let integer_digits = (significant.len() as i64).saturating_sub(exponent);
+
+    exponent <= scale && integer_digits <= i64::from(precision) - scale
+}
+
+// 小数 scale 桁に銀行丸め (最近接偶数への丸め) で丸める
+pub fn round_half_even(value: &BigDecimal, scale: i64) -> BigDecimal {
+    let (digits, exponent) = value.as_bigint_and_exponent();
+    if exponent <= scale {
+        return value.with_scale(scale);
+    }
+
+    // 切り捨てる桁数が値の桁数より多ければ、値は丸める単位の半分未満なので 0 になる
+    let shift = match u32::try_from(exponent - scale) {
+        Ok(shift) if u64::from(shift) <= digits.bits() + 1 => shift,
+        _ => return BigDecimal::new(BigInt::zero(), scale),
+    };
+    let divisor = BigInt::from(10).pow(shift);
+    // BigInt の除算は 0 方向への切り捨て
+    let quotient: BigInt = &digits / &divisor;
+    let remainder: BigInt = (&digits % &divisor).abs() * 2;
+    let away_from_zero = match remainder.cmp(&divisor) {
+        Ordering::Less => false,
+        Ordering::Greater => true,
+        Ordering::Equal => !(&quotient % BigInt::from(2)).is_zero(),
+    };
+    let rounded = if away_from_zero { quotient + digits.signum() } else { quotient };
+
+    BigDecimal::new(rounded, scale)
+}
+
+// 金額。DB の金額列 DECIMAL(10, 2) に合わせて小数 2 桁の文字列で返す。
+// in_currency で通貨を指定した金額はその通貨の補助単位の桁数で返す (JPY なら "1000")
+#[derive(Debug, Clone)]
+pub struct Money {
+    value: BigDecimal,
+    // 出力する小数部の桁数。比較には使わない
+    scale: i64,
+}
+
+// 通貨ごとの桁数で金額を返すレスポンス。currency は金額の通貨 (口座の金額なら口座の通貨)
+pub trait InCurrency {
+    fn in_currency(self, currency: &str) -> Self;
+}
+
+impl Money {
+    // 金額の列の小数部の桁数
+    pub const SCALE: i64 = 2;
+    // 金額の列の全体の桁数
+    pub const PRECISION: u32 = 10;
+
+    pub fn zero() -> Money {
+        Money::from(BigDecimal::zero())
+    }
+
+    pub fn as_decimal(&self) -> &BigDecimal {
+        &self.value
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.value.is_positive()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.value.is_negative()
+    }
+
+    pub fn abs(&self) -> Money {
+        Money { value: self.value.abs(), scale: self.scale }
+    }
+
+    // 小数 scale 桁で表せるか ("1.50" は 1 桁で表せる)
+    pub fn has_scale(&self, scale: i64) -> bool {
+        round_half_even(&self.value, scale) == self.value
+    }
+
+    // 金額の列に保存できる範囲か
+    pub fn fits_column(&self) -> bool {
+        let limit = BigDecimal::new(BigInt::from(10).pow(Money::PRECISION), Money::SCALE);
+        self.has_scale(Money::SCALE) && self.value.abs() < limit
+    }
+
+    // 通貨の補助単位より細かい端数がないか (JPY なら整数のみ)
+    pub fn fits_currency(&self, currency: &str) -> bool {
+        self.has_scale(minor_units(currency))
+    }
+
+    // 金額の列の桁数 (小数 2 桁) に銀行丸めで丸める
+    pub fn round(&self) -> Money {
+        Money::from(round_half_even(&self.value, Money::SCALE))
+    }
+
+    // rate を掛けて currency の補助単位に銀行丸めで丸める
+    pub fn convert(&self, rate: &BigDecimal, currency: &str) -> Money {
+        Money::from(&self.value * rate).in_currency(currency)
+    }
+}
+
+impl Default for Money {
+    fn default() -> Self {
+        Money::zero()
+    }
+}
+
+impl PartialEq for Money {
+    fn eq(&self, other: &Money) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Money {}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Money) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Money {
+    fn cmp(&self, other: &Money) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl InCurrency for Money {
+    fn in_currency(self, currency: &str) -> Money {
+        let scale = minor_units(currency);
+        Money { value: round_half_even(&self.value, scale), scale }
+    }
+}
+
+impl<T: InCurrency> InCurrency for Option<T> {
+    fn in_currency(self, currency: &str) -> Self {
+        self.map(|value| value.in_currency(currency))
+    }
+}
+
+impl<T: InCurrency> InCurrency for Vec<T> {
+    fn in_currency(self, currency: &str) -> Self {
+        self.into_iter().map(|value| value.in_currency(currency)).collect()
+    }
+}
+
+impl From<BigDecimal> for Money {
+    fn from(value: BigDecimal) -> Self {
+        Money { value, scale: Money::SCALE }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseMoneyError {
+    Invalid(ParseBigDecimalError),
+    // 金額の列に保存できない桁数。丸めなどの計算をする前に拒否する
+    OutOfRange,
+}
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseMoneyError::Invalid(e) => write!(f, "{}", e),
+            ParseMoneyError::OutOfRange => f.write_str(&amount_limits(Money::SCALE)),
+        }
+    }
+}
+
+impl Error for ParseMoneyError {}
+
+impl FromStr for Money {
+    type Err = ParseMoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = BigDecimal::from_str(s).map_err(ParseMoneyError::Invalid)?;
+        if fits_digits(&value, Money::PRECISION, Money::SCALE) {
+            Ok(Money::from(value))
+        } else {
+            Err(ParseMoneyError::OutOfRange)
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", round_half_even(&self.value, self.scale))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+// 金額の列に保存できない桁数の値はデシリアライズの時点で拒否する
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Money::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Type<Postgres> for Money {
+    fn type_info() -> PgTypeInfo {
+        <BigDecimal as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <BigDecimal as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for Money {
+    fn encode_by_ref(&self, buf: &mut sqlx::postgres::PgArgumentBuffer) -> sqlx::encode::IsNull {
+        <BigDecimal as Encode<Postgres>>::encode_by_ref(&self.value, buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Money {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        <BigDecimal as Decode<Postgres>>::decode(value).map(Money::from)
+    }
+}
+
+impl Add for &Money {
+    type Output = Money;
+
+    fn add(self, other: &Money) -> Money {
+        Money { value: &self.value + &other.value, scale: self.scale.max(other.scale) }
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money { value: self.value + other.value, scale: self.scale.max(other.scale) }
+    }
+}
+
+impl AddAssign<&Money> for Money {
+    fn add_assign(&mut self, other: &Money) {
+        self.value += &other.value;
+        self.scale = self.scale.max(other.scale);
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.value += other.value;
+        self.scale = self.scale.max(other.scale);
+    }
+}
+
+impl Sub for &Money {
+    type Output = Money;
+
+    fn sub(self, other: &Money) -> Money {
+        Money { value: &self.value - &other.value, scale: self.scale.max(other.scale) }
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money { value: self.value - other.value, scale: self.scale.max(other.scale) }
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money { value: -self.value, scale: self.scale }
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), |total, amount| &total + amount)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), |total, amount| total + amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error as DeError, StrDeserializer};
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn round_half_even_rounds_ties_to_even() {
+        assert_eq!(round_half_even(&decimal("0.125"), 2), decimal("0.12"));
+        assert_eq!(round_half_even(&decimal("0.135"), 2), decimal("0.14"));
+        assert_eq!(round_half_even(&decimal("0.1251"), 2), decimal("0.13"));
+        assert_eq!(round_half_even(&decimal("2.5"), 0), decimal("2"));
+        assert_eq!(round_half_even(&decimal("3.5"), 0), decimal("4"));
+    }
+
+    #[test]
+    fn round_half_even_rounds_negative_values_symmetrically() {
+        assert_eq!(round_half_even(&decimal("-0.125"), 2), decimal("-0.12"));
+        assert_eq!(round_half_even(&decimal("-0.135"), 2), decimal("-0.14"));
+        assert_eq!(round_half_even(&decimal("-0.126"), 2), decimal("-0.13"));
+    }
+
+    #[test]
+    fn round_half_even_keeps_values_already_at_scale() {
+        let rounded = round_half_even(&decimal("12.3"), 2);
+        assert_eq!(rounded, decimal("12.30"));
+        assert_eq!(rounded.to_string(), "12.30");
+        assert_eq!(round_half_even(&decimal("100"), 2).to_string(), "100.00");
+    }
+
+    #[test]
+    fn round_half_even_returns_zero_for_tiny_exponents() {
+        assert_eq!(round_half_even(&decimal("1e-10000000"), 2), BigDecimal::zero());
+        assert_eq!(round_half_even(&decimal("-9e-30"), 2), BigDecimal::zero());
+    }
+
+    #[test]
+    fn minor_units_follow_the_currency() {
+        assert_eq!(minor_units("USD"), 2);
+        assert_eq!(minor_units("JPY"), 0);
+        assert!(is_supported_currency("EUR"));
+        assert!(!is_supported_currency("KWD"));
+    }
+
+    #[test]
+    fn amount_limits_follow_the_scale() {
+        assert_eq!(amount_limits(2), "must have at most 2 decimal places and be between -99999999.99 and 99999999.99");
+        assert_eq!(amount_limits(0), "must be a whole number between -99999999 and 99999999");
+    }
+
+    #[test]
+    fn money_rejects_values_outside_the_column() {
+        assert!(matches!(Money::from_str("1e-10000000"), Err(ParseMoneyError::OutOfRange)));
+        assert!(matches!(Money::from_str("1e10000000"), Err(ParseMoneyError::OutOfRange)));
+        assert!(matches!(Money::from_str("12.345"), Err(ParseMoneyError::OutOfRange)));
+        assert!(matches!(Money::from_str("100000000"), Err(ParseMoneyError::OutOfRange)));
+        assert!(matches!(Money::from_str("abc"), Err(ParseMoneyError::Invalid(_))));
+    }
+
+    #[test]
+    fn money_accepts_values_that_fit_the_column() {
+        assert_eq!(Money::from_str("99999999.99").unwrap().to_string(), "99999999.99");
+        assert_eq!(Money::from_str("-0.01").unwrap().to_string(), "-0.01");
+        assert_eq!(Money::from_str("1.500").unwrap().to_string(), "1.50");
+        assert_eq!(Money::from_str("1e2").unwrap().to_string(), "100.00");
+    }
+
+    #[test]
+    fn money_serializes_with_the_currency_scale() {
+        let amount = Money::from(decimal("1000.00"));
+        assert_eq!(amount.to_string(), "1000.00");
+        assert_eq!(amount.clone().in_currency("JPY").to_string(), "1000");
+        assert_eq!(amount.in_currency("USD").to_string(), "1000.00");
+        assert_eq!(Money::from(decimal("12.5")).in_currency("JPY").to_string(), "12");
+        assert_eq!(Money::from(decimal("10")).convert(&decimal("0.0667"), "JPY").to_string(), "1");
+        assert_eq!(Money::from(decimal("1000")).in_currency("JPY"), Money::from(decimal("1000.00")));
+    }
+
+    #[test]
+    fn money_deserializes_only_values_that_fit_the_column() {
+        let deserialize = |value| Money::deserialize(StrDeserializer::<DeError>::new(value));
+        assert!(deserialize("12.34").is_ok());
+        assert!(deserialize("1e-10000000").is_err());
+    }
+}
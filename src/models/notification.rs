@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use validator::Validate;
+
+// 利用者への通知。予算のしきい値の通知では budget_id と threshold が入る
+#[derive(Serialize)]
+pub struct Notification {
+    pub notification_id: i32,
+    pub user_id: i32,
+    pub budget_id: Option<i32>,
+    pub threshold: Option<i32>,
+    pub notification_title: String,
+    pub notification_message: String,
+    pub created_at: NaiveDateTime,
+    pub read_at: Option<NaiveDateTime>,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+fn default_notification_limit() -> i64 {
+    50
+}
+
+// GET /notifications のクエリパラメータ
+#[derive(Deserialize, Validate)]
+pub struct NotificationListQuery {
+    // true の場合は未読の通知だけを返す
+    #[serde(default)]
+    pub unread: bool,
+    #[serde(default = "default_notification_limit")]
+    #[validate(range(min = 1, max = 200))]
+    pub limit: i64,
+}
+
+// PUT /notifications/:id のリクエストボディ
+#[derive(Deserialize, Validate)]
+pub struct NotificationUpdate {
+    pub read: bool,
+}
@@ -0,0 +1,64 @@
+use chrono::{Datelike, Local, Months, NaiveDate};
+use serde::{Deserialize, Deserializer};
+
+// 集計単位。PostgreSQL の date_trunc に渡す値と対応する
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Granularity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+            Granularity::Year => "year",
+        }
+    }
+}
+
+// "YYYY-MM" 形式で指定する 1 か月
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Month {
+    first_day: NaiveDate,
+}
+
+impl Month {
+    pub fn current() -> Self {
+        let today = Local::now().date_naive();
+        Month { first_day: today.with_day(1).unwrap_or(today) }
+    }
+
+    pub fn first_day(&self) -> NaiveDate {
+        self.first_day
+    }
+
+    // n か月前の月
+    pub fn months_before(&self, n: u32) -> Month {
+        Month { first_day: self.first_day.checked_sub_months(Months::new(n)).unwrap_or(self.first_day) }
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        self.first_day
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(self.first_day)
+    }
+}
+
+impl<'de> Deserialize<'de> for Month {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+            .map(|first_day| Month { first_day })
+            .map_err(|_| serde::de::Error::custom(format!("invalid month \"{}\", expected YYYY-MM", value)))
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use validator::{Validate, ValidationError};
+use crate::models::money::{InCurrency, Money};
+use crate::models::transaction::TransactionType;
+use crate::validation::{schema_error, validate_nonzero_amount};
+
+#[derive(Deserialize, Serialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+fn default_interval_count() -> i32 {
+    1
+}
+
+// 口座とカテゴリに紐づく定期取引。発生日が来るとバックグラウンドで取引として計上される
+#[derive(Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_schedule", skip_on_field_errors = false))]
+pub struct RecurringSchedule {
+    pub schedule_id: Option<i32>,
+    pub account_id: i32,
+    pub child_category_id: i32,
+    // 取引と同じく transaction_type はカテゴリの種別に合わせ、逆向きのお金の動きは負の金額で表す
+    #[validate(custom(function = "validate_nonzero_amount"))]
+    pub transaction_amount: Money,
+    pub transaction_type: TransactionType,
+    pub transaction_description: Option<String>,
+    pub frequency: Frequency,
+    #[serde(default = "default_interval_count")]
+    #[validate(range(min = 1, max = 366))]
+    pub interval_count: i32,
+    // 月次の計上日。-1 は月末、省略時は start_date の日
+    pub day_of_month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    #[validate(range(min = 1))]
+    pub occurrence_count: Option<i32>,
+    #[serde(default, skip_deserializing)]
+    pub posted_through: Option<NaiveDate>,
+}
+
+impl InCurrency for RecurringSchedule {
+    fn in_currency(self, currency: &str) -> Self {
+        RecurringSchedule {
+            transaction_amount: self.transaction_amount.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+fn validate_schedule(schedule: &RecurringSchedule) -> Result<(), ValidationError> {
+    if let Some(day) = schedule.day_of_month {
+        if schedule.frequency != Frequency::Monthly {
+            return Err(schema_error("day_of_month", "monthly_only", "can only be set for monthly schedules"));
+        }
+        if !(1..=31).contains(&day) && day != -1 {
+            return Err(schema_error("day_of_month", "range", "must be between 1 and 31, or -1 for the last day of the month"));
+        }
+    }
+
+    match schedule.end_date {
+        Some(end_date) if end_date < schedule.start_date => {
+            Err(schema_error("end_date", "date_range", "must not be before start_date"))
+        }
+        _ => Ok(()),
+    }
+}
+
+// 月初の日付 first と同じ月の day 日。月の日数を超える場合と -1 は月末になる
+fn day_in_month(first: NaiveDate, day: i32) -> Option<NaiveDate> {
+    let last_day = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+    let day = if day == -1 { last_day } else { (day as u32).min(last_day) };
+    first.with_day(day)
+}
+
+impl RecurringSchedule {
+    // n 回目 (0 始まり) の周期に当たる日付
+    fn nth_date(&self, n: u32) -> Option<NaiveDate> {
+        let step = n.checked_mul(self.interval_count as u32)?;
+        match self.frequency {
+            Frequency::Daily => self.start_date.checked_add_days(Days::new(step as u64)),
+            Frequency::Weekly => self.start_date.checked_add_days(Days::new(step as u64 * 7)),
+            Frequency::Monthly => {
+                let first = self.start_date.with_day(1)?.checked_add_months(Months::new(step))?;
+                day_in_month(first, self.day_of_month.unwrap_or(self.start_date.day() as i32))
+            }
+            Frequency::Yearly => {
+                let first = self.start_date.with_day(1)?.checked_add_months(Months::new(step.checked_mul(12)?))?;
+                day_in_month(first, self.start_date.day() as i32)
+            }
+        }
+    }
+
+    // 発生日を古い順に返す。終了日も回数もなければ無限に続く
+    pub fn occurrences(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        let count = self.occurrence_count.map_or(usize::MAX, |count| count as usize);
+        (0..)
+            .map_while(|n| self.nth_date(n))
+            .filter(|date| *date >= self.start_date)
+            .take(count)
+            .take_while(|date| self.end_date.is_none_or(|end_date| *date <= end_date))
+    }
+
+    // まだ計上されていない発生日
+    pub fn pending_occurrences(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.occurrences()
+            .skip_while(|date| self.posted_through.is_some_and(|posted_through| *date <= posted_through))
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SchedulePreviewQuery {
+    pub until: Option<NaiveDate>,
+    #[serde(default = "default_preview_limit")]
+    #[validate(range(min = 1, max = 366))]
+    pub limit: i64,
+}
+
+fn default_preview_limit() -> i64 {
+    12
+}
+
+#[derive(Serialize)]
+pub struct ScheduledOccurrence {
+    pub schedule_id: i32,
+    pub occurrence_date: NaiveDate,
+    pub account_id: i32,
+    pub child_category_id: i32,
+    pub transaction_amount: Money,
+    pub transaction_type: TransactionType,
+}
+
+impl InCurrency for ScheduledOccurrence {
+    fn in_currency(self, currency: &str) -> Self {
+        ScheduledOccurrence {
+            transaction_amount: self.transaction_amount.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn schedule(frequency: Frequency, start_date: &str) -> RecurringSchedule {
+        RecurringSchedule {
+            schedule_id: Some(1),
+            account_id: 1,
+            child_category_id: 1,
+            transaction_amount: Money::from(sqlx::types::BigDecimal::from(100)),
+            transaction_type: TransactionType::Expense,
+            transaction_description: None,
+            frequency,
+            interval_count: 1,
+            day_of_month: None,
+            start_date: date(start_date),
+            end_date: None,
+            occurrence_count: None,
+            posted_through: None,
+        }
+    }
+
+    fn pending(schedule: &RecurringSchedule, limit: usize) -> Vec<NaiveDate> {
+        schedule.pending_occurrences().take(limit).collect()
+    }
+
+    #[test]
+    fn monthly_occurrences_clamp_to_the_end_of_short_months() {
+        let schedule = schedule(Frequency::Monthly, "2026-01-31");
+        assert_eq!(
+            pending(&schedule, 4),
+            [date("2026-01-31"), date("2026-02-28"), date("2026-03-31"), date("2026-04-30")]
+        );
+    }
+
+    #[test]
+    fn day_of_month_minus_one_is_the_last_day_of_each_month() {
+        let schedule = RecurringSchedule { day_of_month: Some(-1), ..schedule(Frequency::Monthly, "2028-01-15") };
+        assert_eq!(
+            pending(&schedule, 3),
+            [date("2028-01-31"), date("2028-02-29"), date("2028-03-31")]
+        );
+    }
+
+    #[test]
+    fn occurrences_before_the_start_date_are_skipped() {
+        let schedule = RecurringSchedule { day_of_month: Some(1), ..schedule(Frequency::Monthly, "2026-10-05") };
+        assert_eq!(pending(&schedule, 2), [date("2026-11-01"), date("2026-12-01")]);
+    }
+
+    #[test]
+    fn yearly_occurrences_on_february_29_fall_back_to_february_28() {
+        let schedule = schedule(Frequency::Yearly, "2028-02-29");
+        assert_eq!(
+            pending(&schedule, 3),
+            [date("2028-02-29"), date("2029-02-28"), date("2030-02-28")]
+        );
+    }
+
+    #[test]
+    fn weekly_occurrences_follow_the_interval() {
+        let schedule = RecurringSchedule { interval_count: 2, ..schedule(Frequency::Weekly, "2026-10-01") };
+        assert_eq!(
+            pending(&schedule, 3),
+            [date("2026-10-01"), date("2026-10-15"), date("2026-10-29")]
+        );
+    }
+
+    #[test]
+    fn occurrence_count_limits_the_occurrences() {
+        let schedule = RecurringSchedule { occurrence_count: Some(3), ..schedule(Frequency::Daily, "2026-10-01") };
+        assert_eq!(
+            pending(&schedule, 10),
+            [date("2026-10-01"), date("2026-10-02"), date("2026-10-03")]
+        );
+    }
+
+    #[test]
+    fn end_date_limits_the_occurrences() {
+        let schedule = RecurringSchedule { end_date: Some(date("2026-12-15")), ..schedule(Frequency::Monthly, "2026-10-20") };
+        assert_eq!(pending(&schedule, 10), [date("2026-10-20"), date("2026-11-20")]);
+    }
+
+    #[test]
+    fn posted_occurrences_are_not_pending() {
+        let schedule = RecurringSchedule {
+            occurrence_count: Some(3),
+            posted_through: Some(date("2026-11-30")),
+            ..schedule(Frequency::Monthly, "2026-10-10")
+        };
+        assert_eq!(pending(&schedule, 10), [date("2026-12-10")]);
+
+        let finished = RecurringSchedule { posted_through: Some(date("2026-12-10")), ..schedule };
+        assert_eq!(pending(&finished, 10), []);
+    }
+}
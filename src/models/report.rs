@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use sqlx::types::BigDecimal;
+use validator::{Validate, ValidationError};
+use crate::models::money::{InCurrency, Money};
+use crate::models::period::{Granularity, Month};
+use crate::serializers::bigdecimal_serde;
+use crate::validation::schema_error;
+
+fn default_granularity() -> Granularity {
+    Granularity::Month
+}
+
+// GET /reports/summary のクエリパラメータ
+#[derive(Deserialize, Validate)]
+#[validate(schema(function = "validate_summary_range"))]
+pub struct SummaryReportQuery {
+    pub user_id: i32,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[serde(default = "default_granularity")]
+    pub granularity: Granularity,
+}
+
+fn validate_summary_range(query: &SummaryReportQuery) -> Result<(), ValidationError> {
+    if query.to < query.from {
+        return Err(schema_error("to", "date_range", "must not be before from"));
+    }
+    // 日次で 10 年を超える系列は返さない
+    if query.granularity == Granularity::Day && (query.to - query.from).num_days() > 3660 {
+        return Err(schema_error("from", "date_range", "daily summaries are limited to 10 years"));
+    }
+    Ok(())
+}
+
+// 収入と支出の合計。savings_rate は収入に対する net_savings の割合 (%) で、収入がなければ null
+#[derive(Serialize)]
+pub struct SummaryTotals {
+    pub income: Money,
+    pub expense: Money,
+    pub net_savings: Money,
+    #[serde(with = "bigdecimal_serde::option")]
+    pub savings_rate: Option<BigDecimal>,
+}
+
+impl InCurrency for SummaryTotals {
+    fn in_currency(self, currency: &str) -> Self {
+        SummaryTotals {
+            income: self.income.in_currency(currency),
+            expense: self.expense.in_currency(currency),
+            net_savings: self.net_savings.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+// 集計単位ごとの合計。最初と最後の期間は from と to で切り詰める
+#[derive(Serialize)]
+pub struct SummaryPeriod {
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    #[serde(flatten)]
+    pub totals: SummaryTotals,
+}
+
+impl InCurrency for SummaryPeriod {
+    fn in_currency(self, currency: &str) -> Self {
+        SummaryPeriod {
+            totals: self.totals.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SummaryReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    // 金額の通貨 (利用者の基準通貨)
+    pub currency: String,
+    pub periods: Vec<SummaryPeriod>,
+    pub total: SummaryTotals,
+}
+
+// GET /reports/categories のクエリパラメータ。account_id を省略した場合は全口座を集計する
+#[derive(Deserialize, Validate)]
+#[validate(schema(function = "validate_category_report_range"))]
+pub struct CategoryReportQuery {
+    pub user_id: i32,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub account_id: Option<i32>,
+}
+
+fn validate_category_report_range(query: &CategoryReportQuery) -> Result<(), ValidationError> {
+    if query.to < query.from {
+        return Err(schema_error("to", "date_range", "must not be before from"));
+    }
+    Ok(())
+}
+
+// share は全体の支出に対する割合 (%)
+#[derive(Serialize)]
+pub struct ChildCategoryTotal {
+    pub child_category_id: i32,
+    pub child_category_name: String,
+    pub total: Money,
+    #[serde(with = "bigdecimal_serde::option")]
+    pub share: Option<BigDecimal>,
+}
+
+impl InCurrency for ChildCategoryTotal {
+    fn in_currency(self, currency: &str) -> Self {
+        ChildCategoryTotal {
+            total: self.total.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ParentCategoryTotal {
+    pub parent_category_id: i32,
+    pub parent_category_name: String,
+    pub color: String,
+    pub total: Money,
+    #[serde(with = "bigdecimal_serde::option")]
+    pub share: Option<BigDecimal>,
+    pub children: Vec<ChildCategoryTotal>,
+}
+
+impl InCurrency for ParentCategoryTotal {
+    fn in_currency(self, currency: &str) -> Self {
+        ParentCategoryTotal {
+            total: self.total.in_currency(currency),
+            children: self.children.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CategoryReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub currency: String,
+    pub total: Money,
+    pub categories: Vec<ParentCategoryTotal>,
+}
+
+// 比較対象の月
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonBase {
+    PreviousMonth,
+    PreviousYear,
+}
+
+fn default_comparison_base() -> ComparisonBase {
+    ComparisonBase::PreviousMonth
+}
+
+fn default_average_months() -> u32 {
+    3
+}
+
+fn default_deviation_threshold() -> i32 {
+    50
+}
+
+// GET /reports/comparison のクエリパラメータ。period を省略した場合は今月
+#[derive(Deserialize, Validate)]
+pub struct ComparisonReportQuery {
+    pub user_id: i32,
+    pub period: Option<Month>,
+    #[serde(default = "default_comparison_base")]
+    pub compare_to: ComparisonBase,
+    // 平均に使う period の直前の月数
+    #[serde(default = "default_average_months")]
+    #[validate(range(min = 1, max = 24))]
+    pub average_months: u32,
+    // 平均からの乖離率 (%) がこの値以上のカテゴリに significant を付ける
+    #[serde(default = "default_deviation_threshold")]
+    #[validate(range(min = 1, max = 1000))]
+    pub deviation_threshold: i32,
+}
+
+// 子カテゴリごとの支出の比較。change_percent と deviation_percent は比較対象が 0 の場合 null
+#[derive(Serialize)]
+pub struct CategoryComparison {
+    pub child_category_id: i32,
+    pub child_category_name: String,
+    pub parent_category_id: i32,
+    pub parent_category_name: String,
+    pub current: Money,
+    pub baseline: Money,
+    pub change: Money,
+    #[serde(with = "bigdecimal_serde::option")]
+    pub change_percent: Option<BigDecimal>,
+    pub average: Money,
+    #[serde(with = "bigdecimal_serde::option")]
+    pub deviation_percent: Option<BigDecimal>,
+    pub significant: bool,
+}
+
+impl InCurrency for CategoryComparison {
+    fn in_currency(self, currency: &str) -> Self {
+        CategoryComparison {
+            current: self.current.in_currency(currency),
+            baseline: self.baseline.in_currency(currency),
+            change: self.change.in_currency(currency),
+            average: self.average.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ComparisonReport {
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub baseline_start: NaiveDate,
+    pub baseline_end: NaiveDate,
+    pub average_start: NaiveDate,
+    pub average_months: u32,
+    pub currency: String,
+    pub categories: Vec<CategoryComparison>,
+}
@@ -1,16 +1,171 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
-use sqlx::types::BigDecimal;
-use crate::serializers::bigdecimal_serde;
+use sqlx::types::Json;
+use std::fmt;
+use validator::{Validate, ValidationError};
+use crate::models::money::{amount_limits, InCurrency, Money};
+use crate::models::parent_category::CategoryType;
+use crate::validation::{schema_error, validate_currency_code, validate_money, validate_nonzero_amount};
 
-#[derive(Deserialize, Serialize)]
+// 取引の種別。DB には CategoryType と同じ値 (1: 収入, 2: 支出) で保存し、カテゴリの種別とそのまま比較する
+#[derive(Deserialize, Serialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[repr(i32)]
+pub enum TransactionType {
+    Income = 1,
+    Expense = 2,
+}
+
+impl TransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::Income => "income",
+            TransactionType::Expense => "expense",
+        }
+    }
+
+    pub fn opposite(&self) -> TransactionType {
+        match self {
+            TransactionType::Income => TransactionType::Expense,
+            TransactionType::Expense => TransactionType::Income,
+        }
+    }
+}
+
+impl From<CategoryType> for TransactionType {
+    fn from(category_type: CategoryType) -> Self {
+        match category_type {
+            CategoryType::Income => TransactionType::Income,
+            CategoryType::Expense => TransactionType::Expense,
+        }
+    }
+}
+
+impl fmt::Display for TransactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_transaction_categories", skip_on_field_errors = false))]
 pub struct Transaction {
     pub transaction_id: Option<i32>,
     pub account_id: i32,
-    pub child_category_id: i32,
-    #[serde(with = "bigdecimal_serde")]
-    pub transaction_amount: BigDecimal,
-    pub transaction_type: String,
+    // 振替の取引と分割された取引はカテゴリを持たない
+    pub child_category_id: Option<i32>,
+    // transaction_type はカテゴリの種別と同じで、払い戻しなど逆向きのお金の動きは負の金額になる
+    #[validate(custom(function = "validate_nonzero_amount"))]
+    pub transaction_amount: Money,
+    pub transaction_type: TransactionType,
     pub transaction_date: NaiveDate,
     pub transaction_description: Option<String>,
+    // 口座と異なる通貨で支払った場合の通貨。NULL なら口座の通貨
+    #[validate(custom(function = "validate_currency_code"))]
+    pub currency: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub transfer_id: Option<i32>,
+    // 1 つの取引を複数のカテゴリに分ける明細
+    #[serde(default)]
+    pub splits: Json<Vec<TransactionSplit>>,
+}
+
+// currency は口座の通貨。口座と異なる通貨の取引はその通貨の桁数で返す
+impl InCurrency for Transaction {
+    fn in_currency(self, currency: &str) -> Self {
+        let currency = self.currency.clone().unwrap_or_else(|| currency.to_string());
+        Transaction {
+            transaction_amount: self.transaction_amount.in_currency(&currency),
+            splits: Json(self.splits.0.in_currency(&currency)),
+            ..self
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TransactionSplit {
+    pub split_id: Option<i32>,
+    pub child_category_id: i32,
+    pub split_amount: Money,
+    pub split_memo: Option<String>,
+}
+
+impl InCurrency for TransactionSplit {
+    fn in_currency(self, currency: &str) -> Self {
+        TransactionSplit { split_amount: self.split_amount.in_currency(currency), ..self }
+    }
+}
+
+// カテゴリは child_category_id か分割行のどちらか一方で指定し、分割行の合計は取引金額と一致させる
+fn validate_transaction_categories(transaction: &Transaction) -> Result<(), ValidationError> {
+    let splits = &transaction.splits.0;
+
+    if splits.is_empty() {
+        return match transaction.child_category_id {
+            Some(_) => Ok(()),
+            None => Err(schema_error("child_category_id", "required", "is required unless splits are given")),
+        };
+    }
+
+    if transaction.child_category_id.is_some() {
+        return Err(schema_error("child_category_id", "split_conflict", "must be omitted when splits are given"));
+    }
+
+    if splits.iter().any(|split| validate_money(&split.split_amount).is_err()) {
+        return Err(schema_error("splits", "precision", format!("every split_amount {}", amount_limits(Money::SCALE))));
+    }
+
+    let positive = transaction.transaction_amount.is_positive();
+    if splits.iter().any(|split| split.split_amount == Money::zero() || split.split_amount.is_positive() != positive) {
+        return Err(schema_error("splits", "sign", "every split_amount must be non-zero and have the same sign as transaction_amount"));
+    }
+
+    let total: Money = splits.iter().map(|split| &split.split_amount).sum();
+    if total != transaction.transaction_amount {
+        return Err(schema_error("splits", "split_total", "split amounts must add up to transaction_amount"));
+    }
+
+    Ok(())
+}
+
+fn default_page_limit() -> i64 {
+    50
+}
+
+// GET /accounts/:id/transactions のクエリパラメータ
+#[derive(Deserialize, Validate)]
+pub struct TransactionListQuery {
+    pub cursor: Option<String>,
+    #[serde(default = "default_page_limit")]
+    #[validate(range(min = 1, max = 200))]
+    pub limit: i64,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub child_category_id: Option<i32>,
+    pub parent_category_id: Option<i32>,
+    pub transaction_type: Option<TransactionType>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_money"))]
+    pub min_amount: Option<Money>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_money"))]
+    pub max_amount: Option<Money>,
+    // transaction_description の部分一致 (大文字小文字を区別しない)
+    #[validate(length(min = 1, max = 100))]
+    pub description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+}
+
+impl InCurrency for TransactionPage {
+    fn in_currency(self, currency: &str) -> Self {
+        TransactionPage {
+            transactions: self.transactions.in_currency(currency),
+            ..self
+        }
+    }
 }
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use validator::{Validate, ValidationError};
+use crate::models::money::{InCurrency, Money};
+use crate::validation::{schema_error, validate_positive_amount};
+
+// 同じユーザーの口座間の資金移動。出金側と入金側の取引をまとめて扱う
+#[derive(Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_transfer_accounts", skip_on_field_errors = false))]
+pub struct Transfer {
+    pub transfer_id: Option<i32>,
+    pub from_account_id: i32,
+    pub to_account_id: i32,
+    #[validate(custom(function = "validate_positive_amount"))]
+    pub transfer_amount: Money,
+    pub transfer_date: NaiveDate,
+    pub transfer_description: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub from_transaction_id: Option<i32>,
+    #[serde(default, skip_deserializing)]
+    pub to_transaction_id: Option<i32>,
+}
+
+impl InCurrency for Transfer {
+    fn in_currency(self, currency: &str) -> Self {
+        Transfer {
+            transfer_amount: self.transfer_amount.in_currency(currency),
+            ..self
+        }
+    }
+}
+
+fn validate_transfer_accounts(transfer: &Transfer) -> Result<(), ValidationError> {
+    if transfer.from_account_id != transfer.to_account_id {
+        Ok(())
+    } else {
+        Err(schema_error("to_account_id", "same_account", "must differ from from_account_id"))
+    }
+}
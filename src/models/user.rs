@@ -1,11 +1,28 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
+use validator::Validate;
+use crate::validation::validate_currency_code;
 
-#[derive(Deserialize, Serialize)]
-pub struct User {
-    pub user_id: Option<i32>,
+// リクエストで受け取るユーザー情報 (パスワードは平文)
+#[derive(Deserialize, Validate)]
+pub struct UserInput {
+    #[validate(length(min = 1, max = 50))]
     pub username: String,
+    #[validate(email, length(max = 100))]
     pub user_email: String,
+    #[validate(length(min = 8, max = 128))]
     pub user_password: String,
+    // レポートを換算する通貨。作成時に省略すると USD、更新時に省略すると変更しない
+    #[validate(custom(function = "validate_currency_code"))]
+    pub base_currency: Option<String>,
+}
+
+// レスポンスとして返すユーザー情報 (パスワードは含めない)
+#[derive(Serialize)]
+pub struct User {
+    pub user_id: i32,
+    pub username: String,
+    pub user_email: String,
+    pub base_currency: String,
     pub created_at: Option<NaiveDateTime>,
-}
\ No newline at end of file
+}
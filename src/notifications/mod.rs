@@ -0,0 +1,193 @@
+pub mod smtp;
+pub mod webhook;
+
+use async_trait::async_trait;
+use sqlx::{query, PgExecutor, PgPool};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use crate::models::notification::Notification;
+
+const DEFAULT_DELIVERY_INTERVAL_SECONDS: u64 = 60;
+// この回数だけ失敗した通知は再送しない
+const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+const DELIVERY_BATCH_SIZE: i64 = 100;
+// LocalDelivery が保持する送信済みの通知の件数
+const LOCAL_DELIVERY_LOG_SIZE: usize = 100;
+
+pub type DeliveryError = Box<dyn std::error::Error + Send + Sync>;
+
+// 通知の送信方法。送信に失敗した通知は次の周期で再送される
+#[async_trait]
+pub trait NotificationDelivery: Send + Sync {
+    async fn deliver(&self, notification: &Notification, user_email: &str) -> Result<(), DeliveryError>;
+}
+
+// 外部に送らず、ログに出して直近の送信済みの通知を保持する。開発環境やテストで使う
+#[derive(Default)]
+pub struct LocalDelivery {
+    delivered: Mutex<VecDeque<(i32, String)>>,
+}
+
+impl LocalDelivery {
+    // 直近に送信した (notification_id, 宛先) の一覧。古いものから LOCAL_DELIVERY_LOG_SIZE 件を超えた分は捨てる
+    pub fn delivered(&self) -> Vec<(i32, String)> {
+        self.delivered.lock().map(|delivered| delivered.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl NotificationDelivery for LocalDelivery {
+    async fn deliver(&self, notification: &Notification, user_email: &str) -> Result<(), DeliveryError> {
+        tracing::info!(
+            "Notification {} for {}: {} - {}",
+            notification.notification_id,
+            user_email,
+            notification.notification_title,
+            notification.notification_message
+        );
+        let mut delivered = self.delivered.lock().map_err(|_| "local delivery log is poisoned")?;
+        if delivered.len() == LOCAL_DELIVERY_LOG_SIZE {
+            delivered.pop_front();
+        }
+        delivered.push_back((notification.notification_id, user_email.to_string()));
+        Ok(())
+    }
+}
+
+// NOTIFICATION_DELIVERY (local / webhook / smtp) に応じた送信方法を作る。既定は local
+pub fn delivery_from_env() -> Arc<dyn NotificationDelivery> {
+    match std::env::var("NOTIFICATION_DELIVERY").as_deref() {
+        Ok("webhook") => Arc::new(webhook::WebhookDelivery::from_env()),
+        Ok("smtp") => Arc::new(smtp::SmtpDelivery::from_env()),
+        Ok("local") | Err(_) => Arc::new(LocalDelivery::default()),
+        Ok(other) => panic!("NOTIFICATION_DELIVERY must be one of local, webhook or smtp, got {}", other),
+    }
+}
+
+pub fn delivery_interval_from_env() -> Duration {
+    let seconds = match std::env::var("NOTIFICATION_DELIVERY_INTERVAL_SECONDS") {
+        Ok(value) => value.parse().expect("NOTIFICATION_DELIVERY_INTERVAL_SECONDS must be an integer"),
+        Err(_) => DEFAULT_DELIVERY_INTERVAL_SECONDS,
+    };
+    Duration::from_secs(seconds)
+}
+
+// 未送信の通知を一定間隔で送るタスクを起動する
+pub fn spawn_dispatcher(db_pool: PgPool, delivery: Arc<dyn NotificationDelivery>, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            match deliver_pending(&db_pool, delivery.as_ref()).await {
+                Ok(0) => {}
+                Ok(delivered) => tracing::info!("Delivered {} notifications", delivered),
+                Err(e) => tracing::error!("Failed to deliver notifications: {}", e),
+            }
+        }
+    })
+}
+
+// 未送信の通知を古い順に送り、送信できた件数を返す。失敗した通知は試行回数とエラーを記録する。
+// 送信中に DB のトランザクションを開いたままにしないよう、先に対象の通知を確保してから 1 件ずつ送って結果を記録する
+pub async fn deliver_pending(db_pool: &PgPool, delivery: &dyn NotificationDelivery) -> Result<u64, sqlx::Error> {
+    // 複数のインスタンスが同じ通知を送らないよう、確保した通知は期限まで他の配信処理から除外する。
+    // 期限は 1 回の配信処理で全件の送信がタイムアウトしても切れない長さにする
+    let mut rows = query!(
+        "UPDATE Notifications n SET delivery_claimed_until = CURRENT_TIMESTAMP + INTERVAL '30 minutes'
+        FROM Users u
+        WHERE u.user_id = n.user_id
+            AND n.notification_id IN (
+                SELECT notification_id FROM Notifications
+                WHERE delivered_at IS NULL
+                    AND delivery_attempts < $1
+                    AND (delivery_claimed_until IS NULL OR delivery_claimed_until < CURRENT_TIMESTAMP)
+                ORDER BY notification_id
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+        RETURNING n.notification_id, n.user_id, n.budget_id, n.threshold, n.notification_title, n.notification_message,
+            n.created_at, n.read_at, n.delivered_at, u.user_email",
+        MAX_DELIVERY_ATTEMPTS,
+        DELIVERY_BATCH_SIZE
+    )
+    .fetch_all(db_pool)
+    .await?;
+    rows.sort_by_key(|row| row.notification_id);
+
+    let mut delivered = 0;
+    for row in rows {
+        let notification = Notification {
+            notification_id: row.notification_id,
+            user_id: row.user_id,
+            budget_id: row.budget_id,
+            threshold: row.threshold,
+            notification_title: row.notification_title,
+            notification_message: row.notification_message,
+            created_at: row.created_at,
+            read_at: row.read_at,
+            delivered_at: row.delivered_at,
+        };
+
+        // 送信した結果はすぐに記録し、後の通知の記録に失敗しても送信済みの通知を再送しない
+        match delivery.deliver(&notification, &row.user_email).await {
+            Ok(()) => {
+                query!(
+                    "UPDATE Notifications SET delivered_at = CURRENT_TIMESTAMP, delivery_attempts = delivery_attempts + 1, last_delivery_error = NULL,
+                        delivery_claimed_until = NULL
+                    WHERE notification_id = $1",
+                    notification.notification_id
+                )
+                .execute(db_pool)
+                .await?;
+                delivered += 1;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to deliver notification {}: {}", notification.notification_id, e);
+                query!(
+                    "UPDATE Notifications SET delivery_attempts = delivery_attempts + 1, last_delivery_error = $1, delivery_claimed_until = NULL
+                    WHERE notification_id = $2",
+                    e.to_string(),
+                    notification.notification_id
+                )
+                .execute(db_pool)
+                .await?;
+            }
+        }
+    }
+
+    Ok(delivered)
+}
+
+// 取引のカテゴリと日付に当たる予算のうち、消化率がしきい値に達したものを通知として記録する。
+// 取引を書き込んだのと同じトランザクションで呼び、記録した件数を返す
+pub async fn record_budget_alerts<'e>(executor: impl PgExecutor<'e>, transaction_ids: &[i32]) -> Result<u64, sqlx::Error> {
+    let recorded = query!(
+        "INSERT INTO Notifications (user_id, budget_id, threshold, notification_title, notification_message)
+        SELECT b.user_id, b.budget_id, t.threshold,
+            format('%s budget reached %s%%', c.child_category_name, t.threshold),
+            format('%s of %s spent (%s%%) for %s to %s', p.spent, b.amount + b.rollover_amount, p.percent_used, b.start_date, b.end_date)
+        FROM Budgets b
+        JOIN ChildCategories c ON c.child_category_id = b.child_category_id
+        CROSS JOIN LATERAL budget_progress(b.budget_id, CURRENT_DATE) p
+        CROSS JOIN LATERAL unnest(b.alert_thresholds) AS t(threshold)
+        WHERE p.percent_used >= t.threshold
+            AND EXISTS (
+                SELECT 1 FROM TransactionCategoryLines l
+                JOIN Accounts a ON a.account_id = l.account_id
+                WHERE l.transaction_id = ANY($1)
+                    AND l.transaction_type = 2
+                    AND l.child_category_id = b.child_category_id
+                    AND l.transaction_date BETWEEN b.start_date AND b.end_date
+                    AND a.user_id = b.user_id
+            )
+        ON CONFLICT (budget_id, threshold) WHERE budget_id IS NOT NULL DO NOTHING",
+        transaction_ids
+    )
+    .execute(executor)
+    .await?
+    .rows_affected();
+
+    Ok(recorded)
+}
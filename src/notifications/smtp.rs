@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use crate::models::notification::Notification;
+use super::{DeliveryError, NotificationDelivery};
+
+// 通知を利用者のメールアドレスに SMTP で送る
+pub struct SmtpDelivery {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpDelivery {
+    pub fn new(transport: AsyncSmtpTransport<Tokio1Executor>, from: Mailbox) -> Self {
+        SmtpDelivery { transport, from }
+    }
+
+    // SMTP_HOST と SMTP_FROM は必須。SMTP_TLS は starttls (既定)、tls、none のいずれか。
+    // none はローカルのテスト用サーバー向けで、認証情報を平文で送る
+    pub fn from_env() -> Self {
+        let host = std::env::var("SMTP_HOST").expect("SMTP_HOST must be set");
+        let from = std::env::var("SMTP_FROM")
+            .expect("SMTP_FROM must be set")
+            .parse()
+            .expect("SMTP_FROM must be a valid mailbox");
+
+        let mut builder = match std::env::var("SMTP_TLS").as_deref() {
+            Ok("starttls") | Err(_) => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).expect("invalid SMTP_HOST"),
+            Ok("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).expect("invalid SMTP_HOST"),
+            Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            Ok(other) => panic!("SMTP_TLS must be one of starttls, tls or none, got {}", other),
+        };
+        if let Ok(port) = std::env::var("SMTP_PORT") {
+            builder = builder.port(port.parse().expect("SMTP_PORT must be an integer"));
+        }
+        if let (Ok(username), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Self::new(builder.build(), from)
+    }
+}
+
+#[async_trait]
+impl NotificationDelivery for SmtpDelivery {
+    async fn deliver(&self, notification: &Notification, user_email: &str) -> Result<(), DeliveryError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(user_email.parse()?)
+            .subject(&notification.notification_title)
+            .body(notification.notification_message.clone())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use crate::models::notification::Notification;
+use super::{DeliveryError, NotificationDelivery};
+
+// 通知を JSON で指定の URL に POST する。2xx 以外の応答は失敗として再送する
+pub struct WebhookDelivery {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookDelivery {
+    pub fn new(url: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("failed to build the webhook HTTP client");
+        WebhookDelivery { client, url }
+    }
+
+    pub fn from_env() -> Self {
+        let url = std::env::var("NOTIFICATION_WEBHOOK_URL").expect("NOTIFICATION_WEBHOOK_URL must be set");
+        Self::new(url)
+    }
+}
+
+#[async_trait]
+impl NotificationDelivery for WebhookDelivery {
+    async fn deliver(&self, notification: &Notification, _user_email: &str) -> Result<(), DeliveryError> {
+        self.client
+            .post(&self.url)
+            .json(notification)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{Local, NaiveDate};
+use sqlx::{query, query_as, query_scalar, PgPool};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use crate::models::recurring::{Frequency, RecurringSchedule};
+use crate::models::transaction::TransactionType;
+use crate::notifications::record_budget_alerts;
+
+const DEFAULT_POST_INTERVAL_SECONDS: u64 = 60 * 60;
+
+pub fn post_interval_from_env() -> Duration {
+    let seconds = match std::env::var("RECURRING_POST_INTERVAL_SECONDS") {
+        Ok(value) => value.parse().expect("RECURRING_POST_INTERVAL_SECONDS must be an integer"),
+        Err(_) => DEFAULT_POST_INTERVAL_SECONDS,
+    };
+    Duration::from_secs(seconds)
+}
+
+// 定期取引の計上を一定間隔で繰り返すタスクを起動する
+pub fn spawn_poster(db_pool: PgPool, period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            let today = Local::now().date_naive();
+            match post_due_occurrences(&db_pool, today).await {
+                Ok(0) => {}
+                Ok(posted) => tracing::info!("Posted {} recurring transactions", posted),
+                Err(e) => tracing::error!("Failed to post recurring transactions: {}", e),
+            }
+        }
+    })
+}
+
+// today までに発生した未計上の取引を作成し、作成した件数を返す
+pub async fn post_due_occurrences(db_pool: &PgPool, today: NaiveDate) -> Result<u64, sqlx::Error> {
+    let schedule_ids = query_scalar!(
+        "SELECT schedule_id FROM RecurringSchedules WHERE next_occurrence <= $1 ORDER BY schedule_id",
+        today
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut posted = 0;
+    for schedule_id in schedule_ids {
+        // 1 件の失敗で他のスケジュールの計上を止めない
+        match post_schedule(db_pool, schedule_id, today).await {
+            Ok(count) => posted += count,
+            Err(e) => tracing::error!("Failed to post recurring schedule {}: {}", schedule_id, e),
+        }
+    }
+
+    Ok(posted)
+}
+
+async fn post_schedule(db_pool: &PgPool, schedule_id: i32, today: NaiveDate) -> Result<u64, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+
+    // 複数のインスタンスが同じスケジュールを同時に計上しないよう行をロックする
+    let schedule = query_as!(
+        RecurringSchedule,
+        r#"SELECT schedule_id, account_id, child_category_id, transaction_amount, transaction_type AS "transaction_type: TransactionType",
+            transaction_description, frequency AS "frequency: Frequency", interval_count, day_of_month, start_date, end_date, occurrence_count, posted_through
+        FROM RecurringSchedules
+        WHERE schedule_id = $1
+        FOR UPDATE SKIP LOCKED"#,
+        schedule_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(schedule) = schedule else {
+        return Ok(0);
+    };
+
+    let mut transaction_ids = Vec::new();
+    for occurrence_date in schedule.pending_occurrences().take_while(|date| *date <= today) {
+        // 一意制約により、同じ発生日の取引が既にあれば何もしない
+        let transaction_id = query_scalar!(
+            "INSERT INTO Transactions (account_id, child_category_id, transaction_amount, transaction_type, transaction_date, transaction_description, schedule_id, occurrence_date)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $5)
+            ON CONFLICT (schedule_id, occurrence_date) WHERE schedule_id IS NOT NULL DO NOTHING
+            RETURNING transaction_id",
+            schedule.account_id,
+            schedule.child_category_id,
+            schedule.transaction_amount.as_decimal(),
+            schedule.transaction_type as i32,
+            occurrence_date,
+            schedule.transaction_description,
+            schedule_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        transaction_ids.extend(transaction_id);
+    }
+
+    record_budget_alerts(&mut *tx, &transaction_ids).await?;
+
+    // today より後の最初の発生日。終了日や回数に達して発生日が残っていなければ None
+    let next_occurrence = schedule.pending_occurrences().find(|date| *date > today);
+
+    query!(
+        "UPDATE RecurringSchedules SET posted_through = $1, next_occurrence = $2 WHERE schedule_id = $3",
+        today,
+        next_occurrence,
+        schedule_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(transaction_ids.len() as u64)
+}
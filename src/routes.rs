@@ -1,23 +1,47 @@
-use axum::{Router, routing::get, routing::post, routing::put};
+use axum::{Router, extract::DefaultBodyLimit, routing::get, routing::post, routing::put, routing::delete};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use crate::db::AppState;
 
 use crate::handlers::{
+    auth::{login, logout, refresh},
     users::{create_user, get_users, get_user, update_user, delete_user},
-    accounts::{create_account, get_account, update_account, delete_account},
+    accounts::{create_account, get_account, update_account, delete_account, get_balance_history},
+    forecast::get_cash_flow_forecast,
     categories::{create_parent_category, create_child_category, get_categories, update_parent_category, update_child_category, delete_parent_category, delete_child_category},
-    transactions::{create_transaction, get_transaction, update_transaction, delete_transaction},
-    budgets::{create_budget, get_budget, update_budget, delete_budget},
+    transactions::{create_transaction, get_transaction, update_transaction, delete_transaction, list_account_transactions},
+    transfers::{create_transfer, get_transfer, update_transfer, delete_transfer},
+    budgets::{create_budget, get_budget, update_budget, delete_budget, get_user_budgets},
+    budget_templates::{create_budget_template, get_budget_template, list_user_budget_templates, update_budget_template, delete_budget_template},
+    exchange_rates::{list_exchange_rates, upload_exchange_rates},
+    export::{export_ledger, import_ledger},
+    imports::{create_import_mapping, list_import_mappings, update_import_mapping, delete_import_mapping, import_statement, list_import_batches, undo_import_batch},
+    notifications::{list_notifications, update_notification, mark_all_notifications_read},
+    reports::{get_summary_report, get_category_report, get_comparison_report},
+    recurring::{create_schedule, get_schedule, list_account_schedules, update_schedule, delete_schedule, preview_schedule},
 };
 
 pub fn create_routes(state: Arc<Mutex<AppState>>) -> Router {
     Router::new()
         .route("/", get(|| async { "Hello, world!" }).post(|| async { "Hello, world!" }))
+        .route("/auth/login", post(login))
+        .route("/auth/logout", post(logout))
+        .route("/auth/refresh", post(refresh))
         .route("/users", post(create_user).get(get_users))
         .route("/users/:id", get(get_user).put(update_user).delete(delete_user))
+        .route("/users/:id/budgets", get(get_user_budgets))
+        .route("/users/:id/budget-templates", get(list_user_budget_templates))
+        .route("/users/:id/export", get(export_ledger))
+        // アーカイブは既定の 2MB を超えることがある
+        .route("/users/:id/import", post(import_ledger).layer(DefaultBodyLimit::max(64 * 1024 * 1024)))
         .route("/accounts", post(create_account))
         .route("/accounts/:id", get(get_account).put(update_account).delete(delete_account))
+        .route("/accounts/:id/transactions", get(list_account_transactions))
+        .route("/accounts/:id/balance-history", get(get_balance_history))
+        .route("/accounts/:id/forecast", get(get_cash_flow_forecast))
+        .route("/accounts/:id/recurring-schedules", get(list_account_schedules))
+        .route("/accounts/:id/import-mappings", get(list_import_mappings))
+        .route("/accounts/:id/imports", post(import_statement).get(list_import_batches))
         .route("/categories/parent", post(create_parent_category))
         .route("/categories/child", post(create_child_category))
         .route("/categories/:id", get(get_categories))
@@ -25,7 +49,24 @@ pub fn create_routes(state: Arc<Mutex<AppState>>) -> Router {
         .route("/categories/child/:id", put(update_child_category).delete(delete_child_category))
         .route("/transactions", post(create_transaction))
         .route("/transactions/:id", get(get_transaction).put(update_transaction).delete(delete_transaction))
+        .route("/transfers", post(create_transfer))
+        .route("/transfers/:id", get(get_transfer).put(update_transfer).delete(delete_transfer))
         .route("/budgets", post(create_budget))
         .route("/budgets/:id", get(get_budget).put(update_budget).delete(delete_budget))
+        .route("/budget-templates", post(create_budget_template))
+        .route("/budget-templates/:id", get(get_budget_template).put(update_budget_template).delete(delete_budget_template))
+        .route("/recurring-schedules", post(create_schedule))
+        .route("/recurring-schedules/:id", get(get_schedule).put(update_schedule).delete(delete_schedule))
+        .route("/recurring-schedules/:id/preview", get(preview_schedule))
+        .route("/import-mappings", post(create_import_mapping))
+        .route("/import-mappings/:id", put(update_import_mapping).delete(delete_import_mapping))
+        .route("/imports/:id", delete(undo_import_batch))
+        .route("/exchange-rates", get(list_exchange_rates).post(upload_exchange_rates))
+        .route("/reports/summary", get(get_summary_report))
+        .route("/reports/categories", get(get_category_report))
+        .route("/reports/comparison", get(get_comparison_report))
+        .route("/notifications", get(list_notifications))
+        .route("/notifications/read-all", post(mark_all_notifications_read))
+        .route("/notifications/:id", put(update_notification))
         .layer(axum::Extension(state))
 }
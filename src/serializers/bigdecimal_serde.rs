@@ -15,4 +15,31 @@ where
 {
     let s = String::deserialize(deserializer)?;
     BigDecimal::from_str(&s).map_err(serde::de::Error::custom)
-}
\ No newline at end of file
+}
+
+// Option<BigDecimal> 用
+pub mod option {
+    use serde::{self, Deserialize, Deserializer, Serializer};
+    use sqlx::types::BigDecimal;
+    use std::str::FromStr;
+
+    pub fn serialize<S>(value: &Option<BigDecimal>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.serialize_str(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<BigDecimal>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => BigDecimal::from_str(&s).map(Some).map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        FromRequest, FromRequestParts, Json, Query, Request,
+    },
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+use sqlx::types::BigDecimal;
+use std::borrow::Cow;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+use crate::error::{ApiError, FieldError};
+use crate::models::money::{amount_limits, fits_digits, is_supported_currency, minor_units, Money};
+
+// JSON をデシリアライズした後、DB に触れる前に Validate を実行する
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+// クエリ文字列用
+pub struct ValidatedQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        value.validate()?;
+        Ok(ValidatedQuery(value))
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::new(rejection.status(), "invalid_query", rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::new(rejection.status(), "invalid_body", rejection.body_text())
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut details = Vec::new();
+        collect_field_errors(&errors, None, &mut details);
+        details.sort_by(|a, b| a.field.cmp(&b.field));
+
+        ApiError::validation(details)
+    }
+}
+
+// ネストした構造体や配列の要素のエラーは "transactions[2].transaction_amount" のようなパスで返す
+fn collect_field_errors(errors: &ValidationErrors, prefix: Option<&str>, details: &mut Vec<FieldError>) {
+    let join = |field: &str| match prefix {
+        Some(prefix) => format!("{}.{}", prefix, field),
+        None => field.to_string(),
+    };
+
+    for (field, kind) in errors.errors() {
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                details.extend(field_errors.iter().map(|error| FieldError {
+                    // 構造体全体に対する検証エラーは schema_error で指定したフィールドに紐づける
+                    field: if *field == "__all__" {
+                        match error.params.get("field").and_then(|field| field.as_str()) {
+                            Some(field) => Some(join(field)),
+                            None => prefix.map(str::to_string),
+                        }
+                    } else {
+                        Some(join(field))
+                    },
+                    code: error.code.to_string(),
+                    message: describe(error),
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, Some(&join(field)), details),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, Some(&format!("{}[{}]", join(field), index)), details);
+                }
+            }
+        }
+    }
+}
+
+fn describe(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+
+    match error.code.as_ref() {
+        "length" => match (error.params.get("min"), error.params.get("max")) {
+            (Some(min), Some(max)) => format!("must be between {} and {} characters", min, max),
+            (None, Some(max)) => format!("must be at most {} characters", max),
+            (Some(min), None) => format!("must be at least {} characters", min),
+            (None, None) => "has an invalid length".to_string(),
+        },
+        "range" => match (error.params.get("min"), error.params.get("max")) {
+            (Some(min), Some(max)) => format!("must be between {} and {}", min, max),
+            (None, Some(max)) => format!("must be at most {}", max),
+            (Some(min), None) => format!("must be at least {}", min),
+            (None, None) => "is out of range".to_string(),
+        },
+        "email" => "must be a valid email address".to_string(),
+        "required" => "is required".to_string(),
+        code => format!("is invalid ({})", code),
+    }
+}
+
+fn error_with_message(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+// 複数のフィールドにまたがる検証 (validate(schema)) のエラーを特定のフィールドに紐づける
+pub fn schema_error(field: &'static str, code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    let mut error = ValidationError::new(code).with_message(message.into());
+    error.add_param(Cow::from("field"), &field);
+    error
+}
+
+// 金額の列 DECIMAL(10, 2) に保存できない金額は DB に渡す前に拒否する
+pub fn validate_money(amount: &Money) -> Result<(), ValidationError> {
+    if !amount.has_scale(Money::SCALE) {
+        return Err(error_with_message("precision", amount_limits(Money::SCALE)));
+    }
+    if !amount.fits_column() {
+        return Err(error_with_message("overflow", amount_limits(Money::SCALE)));
+    }
+    Ok(())
+}
+
+pub fn validate_positive_amount(amount: &Money) -> Result<(), ValidationError> {
+    validate_money(amount)?;
+    if amount.is_positive() {
+        Ok(())
+    } else {
+        Err(error_with_message("positive", "must be greater than zero"))
+    }
+}
+
+// 取引の金額。負の値はカテゴリの種別と逆向きのお金の動き (払い戻しなど) を表す
+pub fn validate_nonzero_amount(amount: &Money) -> Result<(), ValidationError> {
+    validate_money(amount)?;
+    if *amount == Money::zero() {
+        Err(error_with_message("nonzero", "must not be zero"))
+    } else {
+        Ok(())
+    }
+}
+
+// 為替レートの列 DECIMAL(20, 10) に収まる正の値
+pub fn validate_exchange_rate(rate: &BigDecimal) -> Result<(), ValidationError> {
+    if *rate <= BigDecimal::from(0) {
+        return Err(error_with_message("positive", "must be greater than zero"));
+    }
+    if !fits_digits(rate, 20, 10) {
+        return Err(error_with_message("precision", "must have at most 10 integer and 10 decimal digits"));
+    }
+    Ok(())
+}
+
+// 通貨の補助単位より細かい金額 (JPY の小数など) を拒否する。amounts はフィールド名と金額の組
+pub fn validate_minor_units<'a>(
+    currency: &str,
+    amounts: impl IntoIterator<Item = (String, &'a Money)>,
+) -> Result<(), ApiError> {
+    let details: Vec<FieldError> = amounts
+        .into_iter()
+        .filter(|(_, amount)| !amount.fits_currency(currency))
+        .map(|(field, _)| FieldError {
+            field: Some(field),
+            code: "minor_units".to_string(),
+            message: format!("{} amounts {}", currency, amount_limits(minor_units(currency))),
+        })
+        .collect();
+
+    if details.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(details))
+    }
+}
+
+// 予算の通知しきい値 (%)。重複は認めない
+pub fn validate_alert_thresholds(thresholds: &[i32]) -> Result<(), ValidationError> {
+    if thresholds.len() > 10 {
+        return Err(error_with_message("length", "must not contain more than 10 thresholds"));
+    }
+    if thresholds.iter().any(|threshold| !(1..=1000).contains(threshold)) {
+        return Err(error_with_message("range", "each threshold must be between 1 and 1000"));
+    }
+    if thresholds.iter().enumerate().any(|(i, threshold)| thresholds[..i].contains(threshold)) {
+        return Err(error_with_message("duplicate", "must not contain duplicate thresholds"));
+    }
+    Ok(())
+}
+
+// ISO 4217 の通貨コード (大文字 3 文字)
+pub fn validate_currency_code(currency: &str) -> Result<(), ValidationError> {
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(error_with_message("currency", "must be a three-letter ISO 4217 currency code"));
+    }
+    if !is_supported_currency(currency) {
+        return Err(error_with_message("unsupported_currency", "currencies with three decimal places are not supported"));
+    }
+    Ok(())
+}
+
+// #RRGGBB 形式のみ許可する
+pub fn validate_hex_color(color: &str) -> Result<(), ValidationError> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    if valid {
+        Ok(())
+    } else {
+        Err(error_with_message("hex_color", "must be a color in #RRGGBB format"))
+    }
+}